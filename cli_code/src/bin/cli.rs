@@ -4,12 +4,14 @@
 
 use std::env;
 use std::fs;
+use std::io::BufRead;
+use std::io::Write;
 use std::path::PathBuf;
 use std::time::Instant;
 
 use clap::Parser;
 use cli_lib::js_executor::JsExecutor;
-use compiler_lib::{CompilationResult, State};
+use compiler_lib::{CompilationResult, GraphFormat, Limits, State, SubtypeResult, TextEdit};
 
 #[derive(Parser)]
 #[command(name = "cli")]
@@ -21,11 +23,137 @@ struct Args {
     /// Directory to cache JS execution results (optional)
     #[arg(long)]
     cache_dir: Option<PathBuf>,
+
+    /// Allow +, -, * and comparison operators to be used on floats as well as ints
+    #[arg(long)]
+    overloaded_arith: bool,
+
+    /// Explain every step by which a value flows to the place where it causes a type error
+    #[arg(long)]
+    verbose_errors: bool,
+
+    /// Infer polymorphic types for unannotated function definitions where possible
+    #[arg(long)]
+    generalize: bool,
+
+    /// Write the types of the top level variables defined by each file to a .mli interface file next to it
+    #[arg(long)]
+    emit_interface: bool,
+
+    /// Check the top level variables defined by each file against the .mli interface file next to it
+    #[arg(long)]
+    check_interface: bool,
+
+    /// Offer to apply the fixes suggested by type errors to the source file
+    #[arg(long)]
+    fix: bool,
+
+    /// Print the inferred type of each explicit _ annotation
+    #[arg(long)]
+    typed_holes: bool,
+
+    /// Print the type constraint graph of each file in the given format (dot or json)
+    #[arg(long, value_name = "FORMAT")]
+    dump_graph: Option<String>,
+
+    /// Disable the warning with the given name (e.g. unused-variable). May be repeated.
+    #[arg(long = "disable-warning", value_name = "NAME")]
+    disabled_warnings: Vec<String>,
+
+    /// Maximum number of nodes in the type graph
+    #[arg(long)]
+    max_type_nodes: Option<usize>,
+
+    /// Maximum number of work items processed when checking a single subtyping constraint
+    #[arg(long)]
+    max_flow_work: Option<usize>,
+
+    /// Maximum depth of nested instantiations of polymorphic types
+    #[arg(long)]
+    max_instantiation_depth: Option<usize>,
+}
+
+/// Describe the position and text of each edit, with 1-based line and column numbers
+fn describe_fix(data: &str, fix: &[TextEdit]) -> String {
+    let parts: Vec<_> = fix
+        .iter()
+        .map(|e| {
+            let line_start = data[..e.start].rfind('\n').map_or(0, |i| i + 1);
+            let line = data[..e.start].matches('\n').count() + 1;
+            let col = e.start - line_start + 1;
+            if e.start == e.end {
+                format!("insert `{}` at {}:{}", e.text, line, col)
+            } else {
+                format!("replace `{}` at {}:{} with `{}`", &data[e.start..e.end], line, col, e.text)
+            }
+        })
+        .collect();
+    parts.join(", ")
+}
+
+/// Ask the user whether to apply each fix suggested for the last processed file, then write the accepted ones
+fn offer_fixes(state: &State, fname: &PathBuf, data: &str) {
+    let source = state.last_source_index();
+    let fixes: Vec<_> = state
+        .suggested_fixes()
+        .iter()
+        .filter(|fix| fix.iter().all(|e| Some(e.source) == source))
+        .collect();
+
+    let mut accepted = Vec::new();
+    let mut stdin = std::io::stdin().lock();
+    for fix in fixes {
+        print!(
+            "Suggested fix: {}\nApply to {}? [y/N] ",
+            describe_fix(data, fix),
+            fname.display()
+        );
+        std::io::stdout().flush().unwrap();
+        let mut answer = String::new();
+        // Treat the end of input (or a failure to read it) as declining this and all remaining fixes
+        if !matches!(stdin.read_line(&mut answer), Ok(n) if n > 0) {
+            println!();
+            break;
+        }
+        if matches!(answer.trim(), "y" | "Y" | "yes") {
+            accepted.extend(fix.iter().cloned());
+        }
+    }
+
+    if !accepted.is_empty() {
+        match fs::write(fname, TextEdit::apply_all(data, &accepted)) {
+            Ok(()) => println!("Updated {}", fname.display()),
+            Err(e) => println!("ERROR\nCould not write {}: {}", fname.display(), e),
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
     let mut state = State::new();
+    state.set_overloaded_arith(args.overloaded_arith);
+    state.set_verbose_errors(args.verbose_errors);
+    state.set_generalize(args.generalize);
+    state.set_typed_holes(args.typed_holes);
+    let mut limits = Limits::default();
+    limits.max_type_nodes = args.max_type_nodes.unwrap_or(limits.max_type_nodes);
+    limits.max_flow_work = args.max_flow_work.unwrap_or(limits.max_flow_work);
+    limits.max_instantiation_depth = args.max_instantiation_depth.unwrap_or(limits.max_instantiation_depth);
+    state.set_limits(limits);
+    for name in args.disabled_warnings {
+        if !state.set_warning_enabled(&name, false) {
+            let names: Vec<_> = State::warning_names().collect();
+            eprintln!("Unknown warning {}. Valid warnings are: {}", name, names.join(", "));
+            std::process::exit(1);
+        }
+    }
+    let graph_format = args.dump_graph.map(|name| {
+        GraphFormat::from_name(&name).unwrap_or_else(|| {
+            eprintln!("Unknown graph format {}. Valid formats are: dot, json", name);
+            std::process::exit(1);
+        })
+    });
+    state.set_record_graph(graph_format.is_some());
     let js_executor = JsExecutor::new(args.cache_dir);
 
     for fname in args.files {
@@ -37,6 +165,37 @@ fn main() {
         dbg!(t0.elapsed());
 
         println!("{}", res);
+        if args.fix {
+            offer_fixes(&state, &fname, &data);
+        }
+        for (_, msg) in state.warnings() {
+            println!("{}", msg);
+        }
+        for msg in state.typed_holes() {
+            println!("{}", msg);
+        }
+        for (name, ty) in state.defined_bindings() {
+            println!("val {} : {}", name, ty);
+        }
+        if matches!(res, CompilationResult::Success(_)) {
+            let interface_fname = fname.with_extension("mli");
+            if args.emit_interface {
+                match fs::write(&interface_fname, state.interface()) {
+                    Ok(()) => println!("Wrote interface {}", interface_fname.display()),
+                    Err(e) => println!("ERROR\nCould not write interface {}: {}", interface_fname.display(), e),
+                }
+            }
+            if args.check_interface {
+                match fs::read_to_string(&interface_fname).map(|interface| state.check_interface(&interface)) {
+                    Ok(Ok(())) => println!("Matches interface {}", interface_fname.display()),
+                    Ok(Err(msg)) => println!("ERROR\n{}", msg),
+                    Err(e) => println!("ERROR\nCould not read interface {}: {}", interface_fname.display(), e),
+                }
+            }
+        }
+        if let Some(graph) = graph_format.and_then(|format| state.constraint_graph(format)) {
+            println!("{}", graph);
+        }
 
         if let CompilationResult::Success(js_code) = res {
             println!("\nExecuting...");
@@ -58,6 +217,7 @@ mod tests {
     use super::*;
 
     use compiler_lib::CompilationResult;
+    use compiler_lib::GraphFormat;
 
     #[test]
     fn run_tests() {
@@ -89,4 +249,592 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn binding_types() {
+        let mut state = State::new();
+        state.process("let f = fun {a; b} -> a + b; let r = {x=1.5; mut y=\"s\"}; let p = (f, `A 1);");
+        let expected = [
+            ("f", "{a: int; b: int} -> int"),
+            ("r", "{x: float; mut y: str}"),
+            ("p", "({a: int; b: int} -> int) * [`A int]"),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(n, t)| (n.to_owned(), t.to_owned())).collect();
+        assert_eq!(state.defined_bindings(), expected);
+        assert_eq!(state.binding_type("f").as_deref(), Some("{a: int; b: int} -> int"));
+        assert_eq!(state.binding_type("missing"), None);
+    }
+
+    #[test]
+    fn large_types() {
+        // The type of each variable is twice as large as the last when written out
+        let mut state = State::new();
+        let mut src = "let v0 = 1;".to_owned();
+        for i in 1..=30 {
+            src += &format!("let v{} = (v{}, v{});", i, i - 1, i - 1);
+        }
+        assert!(matches!(state.process(&src), CompilationResult::Success(_)));
+
+        let ty = state.binding_type("v30").unwrap();
+        assert!(ty.starts_with("((((") && ty.contains("((int * int) * (int * int))"), "{}", ty);
+        assert!(ty.ends_with("...) * ...) * ..."), "{}", ty);
+        assert!(ty.len() < 100_000);
+        assert_eq!(state.binding_type("v2").as_deref(), Some("(int * int) * (int * int)"));
+
+        // Interfaces write the elided parts as any instead
+        let interface = state.interface();
+        assert!(
+            !interface.contains("...") && interface.contains("* any) * any"),
+            "{}",
+            interface
+        );
+        assert_eq!(state.check_interface(&interface), Ok(()));
+    }
+
+    #[test]
+    fn overloaded_arith() {
+        let mut state = State::new();
+        assert!(matches!(state.process("let _ = 1.5 + 2.0;"), CompilationResult::Error(_)));
+
+        state.set_overloaded_arith(true);
+        for s in [
+            "let _: float = 1.5 + 2.0 * 3.0;",
+            "let _: int = 1 - 2;",
+            "let _: bool = 1.0 < 2.0;",
+        ] {
+            if let CompilationResult::Error(e) = state.process(s) {
+                panic!("Unexpected error:\n{}", e);
+            }
+        }
+        for s in ["let _ = 1 + 2.0;", "let _ = \"a\" * \"b\";", "let _ = 1.0 / 2.0;"] {
+            assert!(matches!(state.process(s), CompilationResult::Error(_)));
+        }
+    }
+    #[test]
+    fn fixed_width_ints() {
+        let mut state = State::new();
+        let src =
+            "let a = 7i32 *%i32 -3i32; let b = 255u8 +%u8 1u8; let c = fun x -> x -%i64 1i64; let d = int_of_u8 b + 1;";
+        let CompilationResult::Success(js) = state.process(src) else {
+            panic!("Unexpected error");
+        };
+        assert!(js.contains("Math.imul(7, -3)"), "{}", js);
+        assert!(js.contains("255+1&255"), "{}", js);
+        assert!(js.contains("BigInt.asIntN(64, "), "{}", js);
+        let expected = [("a", "i32"), ("b", "u8"), ("c", "i64 -> i64"), ("d", "int")];
+        let expected: Vec<_> = expected.iter().map(|&(n, t)| (n.to_owned(), t.to_owned())).collect();
+        assert_eq!(state.defined_bindings(), expected);
+
+        // i32 and u8 are JS numbers, so printing them converts them to BigInts to print as integers
+        let CompilationResult::Success(js) = state.process("print a, -0i32 /%i32 2i32, 1.0;") else {
+            panic!("Unexpected error");
+        };
+        assert!(js.contains("(BigInt($.v0)), (BigInt(Math.trunc(0/2)|0)), 1.0"), "{}", js);
+
+        // Conversions to and from int must be explicit
+        for s in [
+            "let _ = 1i32 + 1;",
+            "let _: int = 1i64;",
+            "let _ = 1u8 +%i32 1i32;",
+            "let _ = 256u8;",
+            "let _ = -1u8;",
+        ] {
+            assert!(matches!(state.process(s), CompilationResult::Error(_)), "{}", s);
+        }
+    }
+    #[test]
+    fn expr_types() {
+        let mut state = State::new();
+        let src = "let f = fun (x: int) -> x + 1;";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let source = state.last_source_index().unwrap();
+        let types: Vec<_> = state
+            .expr_types(source)
+            .into_iter()
+            .map(|e| (&src[e.start..e.end], e.inferred, e.ty))
+            .collect();
+        assert!(
+            types.contains(&("fun (x: int) -> x + 1", true, "int -> int".to_owned())),
+            "{:?}",
+            types
+        );
+        assert!(types.contains(&("x", true, "int".to_owned())), "{:?}", types);
+        assert!(types.contains(&("1", false, "int".to_owned())), "{:?}", types);
+        assert_eq!(types[0].0, "fun (x: int) -> x + 1");
+
+        // Each expression has at most one inferred and one expected type, and calls are inferred too
+        let src2 = "let r = f (f 1); let s = (f 2) + 1;";
+        assert!(matches!(state.process(src2), CompilationResult::Success(_)));
+        let types = state.expr_types(state.last_source_index().unwrap());
+        for (i, t) in types.iter().enumerate() {
+            let key = (t.start, t.end, t.inferred);
+            assert!(
+                !types[..i].iter().any(|t2| (t2.start, t2.end, t2.inferred) == key),
+                "{:?}",
+                types
+            );
+        }
+        let types: Vec<_> = types.into_iter().map(|e| (&src2[e.start..e.end], e.inferred, e.ty)).collect();
+        assert!(types.contains(&("f (f 1)", true, "int".to_owned())), "{:?}", types);
+        assert!(types.contains(&("(f 1)", true, "int".to_owned())), "{:?}", types);
+        assert!(types.contains(&("(f 2)", false, "int".to_owned())), "{:?}", types);
+        // Types are only kept for the most recent script
+        assert!(state.expr_types(source).is_empty());
+
+        // Failed sources have no type information
+        assert!(matches!(state.process("let y = 1 + \"a\";"), CompilationResult::Error(_)));
+        assert!(state.expr_types(state.last_source_index().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn type_at() {
+        let mut state = State::new();
+        let src = "let f = fun x -> x + 1; let s = \"a\" ^ \"b\";";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let source = state.last_source_index().unwrap();
+        let at = |pat: &str| src.find(pat).unwrap();
+        assert_eq!(state.type_at(source, at("fun")).as_deref(), Some("int -> int"));
+        assert_eq!(state.type_at(source, at("1;")).as_deref(), Some("int"));
+        assert_eq!(state.type_at(source, at("\"b")).as_deref(), Some("str"));
+        assert_eq!(state.type_at(source, src.len() + 10), None);
+
+        // Call results are checked against an inference variable rather than inferred directly
+        let src = "let g = fun {a: int; b: int} -> a + b; let r = g   {a=1; b=2};";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let source = state.last_source_index().unwrap();
+        let call = src.find("g   {").unwrap() + 2;
+        assert_eq!(state.type_at(source, call).as_deref(), Some("int"));
+        assert_eq!(state.binding_type("r").as_deref(), Some("int"));
+
+        // Checking types and interfaces doesn't change which script is the last one
+        assert_eq!(state.is_subtype("int", "any"), SubtypeResult::Subtype);
+        assert_eq!(state.check_interface("val r: int;"), Ok(()));
+        assert_eq!(state.last_source_index(), Some(source));
+        assert_eq!(state.type_at(source, call).as_deref(), Some("int"));
+
+        // Failed scripts do not leave stale types behind
+        assert!(matches!(state.process("let t = 1 + \"a\";"), CompilationResult::Error(_)));
+        let source = state.last_source_index().unwrap();
+        assert_eq!(state.type_at(source, 8), None);
+    }
+    #[test]
+    fn multiple_errors() {
+        let mut state = State::new();
+        let src = "let a = 1 + \"x\"; let b = a * 2; let c = undefined_var; let d: str = 42; let e = c.foo;";
+        let CompilationResult::Error(e) = state.process(src) else {
+            panic!("Expected error");
+        };
+        // Errors don't cascade to later uses of a and c
+        assert_eq!(e.matches("TypeError").count(), 2, "{}", e);
+        assert_eq!(e.matches("SyntaxError").count(), 1, "{}", e);
+
+        // The whole script is still rolled back
+        assert!(matches!(state.process("let _ = b;"), CompilationResult::Error(_)));
+        assert!(matches!(state.process("let b = 1;"), CompilationResult::Success(_)));
+
+        // A statement whose flow fails partway is rolled back before checking the rest of the script
+        let src = "let r = {mut v: int = 1}; r.v <- {a=\"s\"}; let x: str = r.v; let y: int = \"t\";";
+        let CompilationResult::Error(e) = state.process(src) else {
+            panic!("Expected error");
+        };
+        assert_eq!(e.matches("TypeError").count(), 3, "{}", e);
+        assert!(e.contains("let x: str = r.v;"), "{}", e);
+        assert!(e.contains("let y: int = \"t\";"), "{}", e);
+
+        // A single bad call is only reported once, rather than once for each constraint it breaks
+        let src = "let both = fun (f: type t. t -> t) -> fun pair -> (let (a, b) = pair; (f a, f b));
+            let add_one = fun x -> x + 1;
+            let r = both add_one (42, \"hello\");";
+        let CompilationResult::Error(e) = state.process(src) else {
+            panic!("Expected error");
+        };
+        assert_eq!(e.matches("TypeError").count(), 1, "{}", e);
+    }
+    #[test]
+    fn warnings() {
+        let mut state = State::new();
+        let src = "let x = 1; let f = fun a -> (let b = 2; let x = 3; let {c; d} = {c=x; d=4}; let _e = 5; c);";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let names: Vec<_> = state.warnings().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            ["unused-parameter", "unused-variable", "shadowing", "unused-pattern-variable"]
+        );
+
+        assert!(state.set_warning_enabled("shadowing", false));
+        assert!(!state.set_warning_enabled("no-such-warning", false));
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        assert_eq!(state.warnings().len(), 3);
+
+        // Top level definitions may be unused or redefined
+        assert!(matches!(
+            state.process("let y = 1; let y = 2;"),
+            CompilationResult::Success(_)
+        ));
+        assert!(state.warnings().is_empty());
+
+        // But local variables that shadow a top level definition are still reported
+        assert!(state.set_warning_enabled("shadowing", true));
+        assert!(matches!(
+            state.process("let y = 3; let g = fun y -> y + 1;"),
+            CompilationResult::Success(_)
+        ));
+        let names: Vec<_> = state.warnings().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["shadowing"]);
+
+        // Discarded values are a warning rather than an error, except for the last statement
+        let src = "let z = (1 + 2; print \"a\"; 3); z; 4";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let names: Vec<_> = state.warnings().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["ignored-result", "ignored-result"]);
+    }
+    #[test]
+    fn unreachable_match_arms() {
+        let mut state = State::new();
+        let src = "let r = (let f = fun x -> match x with | `A a -> a | `B b -> b | _ -> 0; f `A 1 + f `B 2);
+            let s = match `A 1 with | `A a -> a | `B b -> b;";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        let names: Vec<_> = state.warnings().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["unreachable-wildcard", "unreachable-match-arm"]);
+
+        // Top level functions may be called with other variants later
+        let src = "let f = fun x -> match x with | `A a -> a | `B b -> b; let _ = f `A 1;";
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        assert!(state.warnings().is_empty());
+    }
+    #[test]
+    fn limits() {
+        let src = "let id = fun (type t) (x: t): t -> x; let a = id 1; let b = id {x=a; y=[1; 2; 3]};";
+        let expect_limit_err = |limits: Limits| {
+            let mut state = State::new();
+            state.set_limits(limits);
+            match state.process(src) {
+                CompilationResult::Error(e) => assert!(e.contains("exceeded the limit"), "{}", e),
+                CompilationResult::Success(_) => panic!("Expected limit error"),
+            }
+        };
+
+        expect_limit_err(Limits {
+            max_type_nodes: 20,
+            ..Limits::default()
+        });
+
+        expect_limit_err(Limits {
+            max_flow_work: 2,
+            ..Limits::default()
+        });
+
+        expect_limit_err(Limits {
+            max_instantiation_depth: 0,
+            ..Limits::default()
+        });
+
+        let mut state = State::new();
+        state.set_limits(Limits {
+            max_instantiation_depth: 1,
+            ..Limits::default()
+        });
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+    }
+    #[test]
+    fn verbose_errors() {
+        let src = "let f = fun x -> x.a + 1; let g = fun y -> f y; let _ = g {a=\"s\"};";
+        let mut state = State::new();
+        let CompilationResult::Error(short) = state.process(src) else {
+            panic!("Expected error");
+        };
+        assert!(!short.contains("following steps"));
+
+        state.set_verbose_errors(true);
+        let CompilationResult::Error(long) = state.process(src) else {
+            panic!("Expected error");
+        };
+        assert!(long.starts_with(&short), "{}", long);
+        assert!(long.contains("following steps"), "{}", long);
+        assert!(
+            long.contains("1. A value flows into the type expected by this expression"),
+            "{}",
+            long
+        );
+        assert!(long.contains("It passes through the inferred type here"), "{}", long);
+    }
+    #[test]
+    fn is_subtype() {
+        let mut state = State::new();
+        for (a, b) in [
+            ("int", "int"),
+            ("{a: int; b: str}", "{a: int}"),
+            ("[`A int]", "[`A int | `B str]"),
+            ("{a: int} -> int", "{a: int; b: str} -> any"),
+            ("type t. t -> t", "type t. t -> t"),
+        ] {
+            assert_eq!(state.is_subtype(a, b), SubtypeResult::Subtype, "{} <: {}", a, b);
+        }
+
+        let SubtypeResult::NotSubtype(e) = state.is_subtype("{a: int}", "{a: int; b: str}") else {
+            panic!("Expected not subtype");
+        };
+        assert!(e.contains("Missing field b"), "{}", e);
+        assert!(matches!(
+            state.is_subtype("type t. t -> t", "int -> int"),
+            SubtypeResult::NotSubtype(_)
+        ));
+        assert!(matches!(state.is_subtype("int ->", "int"), SubtypeResult::Error(_)));
+        assert!(matches!(state.is_subtype("int", "undefined_type"), SubtypeResult::Error(_)));
+
+        // Checks don't affect later scripts
+        assert!(matches!(state.process("let x: int = 1;"), CompilationResult::Success(_)));
+    }
+
+    #[test]
+    fn generalize() {
+        let src = "let id = fun x -> x; let a = id 1 + 1; let b = id \"s\";";
+        let mut state = State::new();
+        assert!(matches!(state.process(src), CompilationResult::Error(_)));
+
+        state.set_generalize(true);
+        assert!(matches!(state.process(src), CompilationResult::Success(_)));
+        assert_eq!(state.binding_type("id").unwrap(), "type a. a -> a");
+        assert_eq!(state.binding_type("b").unwrap(), "str");
+
+        let res = state.process(
+            "let swap = fun (x, y) -> (y, x); let apply = fun f -> fun x -> f x; let choose = fun x -> if 1 > 0 then x else 0;",
+        );
+        assert!(matches!(res, CompilationResult::Success(_)));
+        assert_eq!(state.binding_type("swap").unwrap(), "type a b. a * b -> b * a");
+        assert_eq!(state.binding_type("apply").unwrap(), "type a b. (a -> b) -> a -> b");
+        assert_eq!(state.binding_type("choose").unwrap(), "type a :> int. a -> a");
+        assert!(state.warnings().is_empty());
+
+        // Functions that can't be generalized keep their monomorphic type, with a warning explaining why
+        for (src, reason) in [
+            (
+                "let r = {mut v=1}; let f = fun x -> (r.v <- x; x); let z = r.v + 1;",
+                "its arguments flow into variables defined outside of the function",
+            ),
+            (
+                "let f = fun x -> (x, x.a);",
+                "its type parameters would need bounds that depend on other type parameters",
+            ),
+            (
+                "let f = fun g -> (g 1, g \"s\");",
+                "its inferred type `type a b. (int | str -> a & b) -> a * b` does not type check",
+            ),
+        ] {
+            assert!(matches!(state.process(src), CompilationResult::Success(_)), "{}", src);
+            let warnings = state.warnings();
+            assert_eq!(warnings.len(), 1, "{:?}", warnings);
+            assert_eq!(warnings[0].0, "not-generalized");
+            assert!(warnings[0].1.contains(reason), "{}", warnings[0].1);
+        }
+        assert_eq!(state.binding_type("f").unwrap(), "(int | str -> any) -> _ * _");
+
+        // Annotated functions are left alone
+        state.process("let f = fun (x: int) -> x;");
+        assert_eq!(state.binding_type("f").unwrap(), "int -> int");
+    }
+    #[test]
+    fn bounded_type_params() {
+        let expect_err = |state: &mut State, src: &str, msg: &str| {
+            let CompilationResult::Error(e) = state.process(src) else {
+                panic!("Expected error for {}", src);
+            };
+            assert!(e.contains(msg), "{}", e);
+        };
+
+        let mut state = State::new();
+        let res = state.process(
+            "let getx = fun (type t <: {x: int}) (r: t) : t * int -> (r, r.x + 1); let (r, n) = getx {x=1; y=\"a\"}; let y = r.y;",
+        );
+        assert!(matches!(res, CompilationResult::Success(_)));
+        assert_eq!(state.binding_type("getx").unwrap(), "type t <: {x: int}. t -> t * int");
+        assert_eq!(state.binding_type("y").unwrap(), "str");
+        expect_err(&mut state, "let _ = getx {y=1};", "Missing field x");
+
+        let res = state
+            .process("let lo = fun (type t :> int) (x: t, y: t) : t -> if 1 > 0 then x else 3; let z = lo (\"a\", \"b\");");
+        assert!(matches!(res, CompilationResult::Success(_)));
+        assert_eq!(state.binding_type("z").unwrap(), "str | int");
+
+        let res = state.process(
+            "let p: {type t <: {a: int}; v: t; f: t -> str} = {v={a=1; b=2}; f=fun r -> \"s\"}; let {type t <: {a: int}; v: t; f: t -> str} = p; let n = v.a; let s = f v;",
+        );
+        assert!(matches!(res, CompilationResult::Success(_)));
+        assert_eq!(state.binding_type("n").unwrap(), "int");
+        expect_err(
+            &mut state,
+            "let p: {type t <: {a: int}; v: t} = {v={b=3}};",
+            "Missing field a",
+        );
+
+        // Bounds are part of the type and must match when comparing polymorphic types
+        expect_err(
+            &mut state,
+            "let g: type t. t -> t * int = getx;",
+            "is required to have an upper bound",
+        );
+        expect_err(
+            &mut state,
+            "let f = fun (g: type a. (type b <: {x: a}. b -> b) -> a) -> 1;",
+            "Type parameter bounds may not refer to type parameters",
+        );
+    }
+    #[test]
+    fn interface() {
+        let mut state = State::new();
+        state.process(
+            "let add = fun (x: int) -> x + 1; let r = {a=1; b=\"s\"}; let pair = fun (type t) (x: t) : t * t -> (x, x);",
+        );
+        let interface = state.interface();
+        assert_eq!(
+            interface,
+            "val add: int -> int;\nval r: {a: int; b: str};\nval pair: type t. t -> t * t;\n"
+        );
+        assert_eq!(state.check_interface(&interface), Ok(()));
+        // Interfaces may hide variables and fields, and give less precise types
+        assert_eq!(state.check_interface(""), Ok(()));
+        assert_eq!(state.check_interface("val r: {a: int}; val add: int -> any;"), Ok(()));
+
+        let err = state.check_interface("val r: {a: int; b: int};").unwrap_err();
+        assert!(
+            err.contains("Variable r does not match its declaration in the interface"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("let add = fun (x: int) -> x + 1; let r = {a=1; b=\"s\"};"),
+            "{}",
+            err
+        );
+        let err = state.check_interface("val missing: int;").unwrap_err();
+        assert!(
+            err.contains("Variable missing is declared in the interface but is not defined"),
+            "{}",
+            err
+        );
+        let err = state.check_interface("value r: int;").unwrap_err();
+        assert!(err.contains("SyntaxError: Expected `val`"), "{}", err);
+
+        // Checking an interface does not constrain the types of the implementation
+        state.process("let id = fun x -> x;");
+        assert!(state.check_interface("val id: int -> int;").is_ok());
+        assert!(state.check_interface("val id: str -> str;").is_ok());
+        assert_eq!(state.binding_type("id").unwrap(), "_ -> _");
+
+        // Types that haven't been inferred yet are written as any or never rather than _
+        state.process("let id = fun x -> x; let r = {mut v = 1};");
+        let interface = state.interface();
+        assert_eq!(interface, "val id: any -> any;\nval r: {mut v: int <- never};\n");
+        assert_eq!(state.check_interface(&interface), Ok(()));
+        state.process("let id = fun (x: int) -> x; let r = {mut v = 1};");
+        let err = state.check_interface(&interface).unwrap_err();
+        assert!(
+            err.contains("Variable id does not match its declaration in the interface"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn suggested_fixes() {
+        let mut state = State::new();
+        let src = "let f = fun x -> x.a;\nlet y = f {b=1} + 2;";
+        assert!(matches!(state.process(src), CompilationResult::Error(_)));
+        let fixes = state.suggested_fixes().to_vec();
+        assert_eq!(fixes.len(), 1);
+        assert!(fixes[0].iter().all(|e| Some(e.source) == state.last_source_index()));
+        assert_eq!(describe_fix(src, &fixes[0]), "insert `(` at 1:13, insert `: _)` at 1:14");
+
+        let fixed = TextEdit::apply_all(src, &fixes[0]);
+        assert_eq!(fixed, "let f = fun (x: _) -> x.a;\nlet y = f {b=1} + 2;");
+        // The fixed script still has the error, but the suggested annotation is now explicit
+        assert!(matches!(state.process(&fixed), CompilationResult::Error(_)));
+        assert!(state.suggested_fixes().is_empty());
+
+        assert!(matches!(state.process("let z = 1;"), CompilationResult::Success(_)));
+        assert!(state.suggested_fixes().is_empty());
+    }
+
+    #[test]
+    fn typed_holes() {
+        let mut state = State::new();
+        state.process("let a: _ = 1;");
+        assert!(state.typed_holes().is_empty());
+
+        state.set_typed_holes(true);
+        state.process("let f = fun (x: _) -> x.a + 1; let y: _ = f {a=3; b=\"x\"};");
+        let holes = state.typed_holes();
+        assert_eq!(holes.len(), 2);
+        assert!(
+            holes[0].starts_with("Found hole _\n  inferred type: {a: int; b: str}\n  used as: {a: int}\n"),
+            "{}",
+            holes[0]
+        );
+        assert!(holes[1].starts_with("Found hole _\n  inferred type: int\n"), "{}", holes[1]);
+
+        // Holes are not reported for scripts with type errors
+        assert!(matches!(state.process("let z: _ = 1 + \"x\";"), CompilationResult::Error(_)));
+        assert!(state.typed_holes().is_empty());
+
+        state.process("let w = fun (x: int) -> (x: _);");
+        assert!(state.typed_holes()[0].contains("inferred type: int"));
+    }
+
+    #[test]
+    fn constraint_graph() {
+        let mut state = State::new();
+        state.process("let f = fun x -> x.a;");
+        assert!(state.constraint_graph(GraphFormat::Dot).is_none());
+
+        state.set_record_graph(true);
+        state.process("let y: _ = f {a=1};");
+        let dot = state.constraint_graph(GraphFormat::Dot).unwrap();
+        assert!(dot.starts_with("digraph"), "{}", dot);
+        assert!(dot.contains("hole: explicit _ annotation"), "{}", dot);
+        assert!(dot.contains("`{a=1}`"), "{}", dot);
+        assert!(dot.contains("lvl 0"), "{}", dot);
+
+        // Graphs are also available for scripts that fail to type check
+        assert!(matches!(state.process("let z = f {b=1};"), CompilationResult::Error(_)));
+        let json = state.constraint_graph(GraphFormat::Json).unwrap();
+        assert!(json.contains("\"kind\": \"use\", \"desc\": \"record type\""), "{}", json);
+        assert!(json.contains("\"reason\": \"root\""), "{}", json);
+    }
+
+    #[test]
+    fn compact() {
+        let mut state = State::new();
+        for src in [
+            "let id = fun x -> x;",
+            "let r = {mut v=1; f=fun (x: int) -> x + 1};",
+            "let apply = fun f -> fun x -> f x;",
+            "let _ = (apply r.f) (id 4); let _ = {a=1; b=\"\"; c=1.0}.b;",
+            "let pair = fun (type t) (x: t): {a: t; b: t} -> {a=x; b=x}; let p = (pair 1).a + 2;",
+        ] {
+            assert!(matches!(state.process(src), CompilationResult::Success(_)), "{}", src);
+        }
+        let types_before = ["id", "r", "apply", "pair"].map(|v| state.binding_type(v));
+
+        let before = state.num_type_nodes();
+        let removed = state.compact();
+        assert!(removed > 0);
+        assert_eq!(state.num_type_nodes(), before - removed);
+        assert_eq!(types_before, ["id", "r", "apply", "pair"].map(|v| state.binding_type(v)));
+        // Compacting again has nothing left to remove
+        assert_eq!(state.compact(), 0);
+
+        // Later scripts can still use the earlier bindings
+        assert!(matches!(
+            state.process("r.v <- 3; let y: int = (apply r.f) (id r.v);"),
+            CompilationResult::Success(_)
+        ));
+        let CompilationResult::Error(e) = state.process("r.v <- \"hello\";") else {
+            panic!("Expected error");
+        };
+        assert!(e.contains("TypeError"), "{}", e);
+        let CompilationResult::Error(e) = state.process("let z: str = (apply r.f) 1;") else {
+            panic!("Expected error");
+        };
+        assert!(e.contains("TypeError"), "{}", e);
+    }
 }
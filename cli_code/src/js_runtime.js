@@ -17,6 +17,18 @@ class Printer {
         this.visit(e);
     }
 
+    // Check whether e is a chain of `Cons (head, tail) ending in `Nil
+    isList(e) {
+        const seen = new Set;
+        while (e && e.$tag === 'Cons' && !seen.has(e)) {
+            seen.add(e);
+            const v = e.$val;
+            if (!v || typeof v !== 'object' || Object.keys(v).length !== 2 || !Object.hasOwn(v, '_0') || !Object.hasOwn(v, '_1')) {return false;}
+            e = v._1;
+        }
+        return !!e && e.$tag === 'Nil';
+    }
+
     visit(e) {
         const type = typeof e;
         if (type === 'boolean' || type === 'bigint') {this.push(e.toString()); return;}
@@ -38,7 +50,17 @@ class Printer {
         const LIMIT = 80;
         if (this.current_size > LIMIT) {this.push('...'); return;}
 
-        if (e.$tag) {
+        if (this.isList(e)) {
+            this.push('[');
+            for (let first = true; e.$tag === 'Cons'; e = e.$val._1) {
+                if (!first) {this.push('; ')}
+                first = false;
+                if (this.current_size > LIMIT) {this.push('...'); break;}
+
+                this.visit(e.$val._0);
+            }
+            this.push(']');
+        } else if (e.$tag) {
             this.push(e.$tag);
             if (!e.$val || typeof e.$val !== 'object') {
                 this.push(' ');
@@ -95,6 +117,40 @@ function loop(expr) {
     return v.$val;
 }
 
+// Structural equality used by the == and != operators
+function eq(a, b) {
+    if (typeof a === 'function' || typeof b === 'function') {
+        throw new Error('Functions cannot be compared for equality');
+    }
+    if (a === b) {return true;}
+    if (typeof a !== 'object' || typeof b !== 'object') {return false;}
+
+    const keys = Object.keys(a);
+    if (keys.length !== Object.keys(b).length) {return false;}
+    return keys.every(k => Object.hasOwn(b, k) && eq(a[k], b[k]));
+}
+function neq(a, b) {
+    return !eq(a, b);
+}
+
+// Ordering used by the compare builtin, whose arguments are always two primitives of the same type
+function cmp(a, b) {
+    // NaN is ordered before all other floats
+    if (a !== a || b !== b) {return (b !== b) - (a !== a);}
+    return a < b ? -1 : (a > b ? 1 : 0);
+}
+function compare({_0: a, _1: b}) {
+    return BigInt(cmp(a, b));
+}
+
+// Conversions between int and the fixed width integer types
+function i32_of_int(x) {return Number(BigInt.asIntN(32, x));}
+function int_of_i32(x) {return BigInt(x);}
+function i64_of_int(x) {return BigInt.asIntN(64, x);}
+function int_of_i64(x) {return x;}
+function u8_of_int(x) {return Number(BigInt.asUintN(8, x));}
+function int_of_u8(x) {return BigInt(x);}
+
 // Global print function
 const printer = new Printer();
 function print(...args) {
@@ -0,0 +1,45 @@
+//! Measures how long it takes to reject a small ill-typed script after a long session.
+//! Rolling back the failed script should only cost time proportional to the changes
+//! it made, so the time per rejection should stay flat as the session grows.
+//!
+//! Run with `cargo bench -p compiler_lib --bench rollback`.
+use std::time::Instant;
+
+use compiler_lib::CompilationResult;
+use compiler_lib::State;
+
+const REJECTIONS: u32 = 200;
+
+fn build_session(definitions: usize) -> State {
+    let mut state = State::new();
+    for i in 0..definitions {
+        let src = format!("let v{i} = {{a={i}; f=fun x -> if x < {i} then x * 2 else x}};");
+        match state.process(&src) {
+            CompilationResult::Success(_) => {}
+            CompilationResult::Error(e) => panic!("{}", e),
+        }
+    }
+    state
+}
+
+fn main() {
+    println!("{:>12} {:>12} {:>16}", "definitions", "type nodes", "per rejection");
+    for definitions in [1000, 4000, 16000] {
+        let mut state = build_session(definitions);
+        let nodes = state.num_type_nodes();
+
+        // The bad script touches a global binding so that rollback has to undo edges added to existing nodes.
+        let bad = format!("let x = v{}.a + \"oops\";", definitions - 1);
+        let start = Instant::now();
+        for _ in 0..REJECTIONS {
+            match state.process(&bad) {
+                CompilationResult::Success(_) => panic!("Bad script unexpectedly compiled"),
+                CompilationResult::Error(_) => {}
+            }
+        }
+        let per_rejection = start.elapsed() / REJECTIONS;
+        assert_eq!(state.num_type_nodes(), nodes);
+
+        println!("{:>12} {:>12} {:>16?}", definitions, nodes, per_rejection);
+    }
+}
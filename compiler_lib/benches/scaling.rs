@@ -0,0 +1,94 @@
+//! Measures how type checking time grows with the size of a script, for a few
+//! different kinds of generated code. Ideally, the time per line stays roughly flat.
+//!
+//! Run with `cargo bench -p compiler_lib --bench scaling`. Pass a pattern name
+//! to only run the matching workloads.
+use std::time::Instant;
+
+use compiler_lib::CompilationResult;
+use compiler_lib::State;
+
+const SIZES: &[usize] = &[625, 1250, 2500, 5000];
+// The transitive closure of the chain pattern grows quadratically, so keep it smaller
+const CHAIN_SIZES: &[usize] = &[50, 100, 200, 400];
+
+// Definitions which don't refer to each other
+fn independent(i: usize) -> String {
+    format!("let v{i} = {{a={i}; f=fun x -> if x < {i} then x * 2 else x}};")
+}
+
+// Each definition uses the previous one
+fn chain(i: usize) -> String {
+    if i == 0 {
+        return "let v0 = {a=0; f=fun x -> x + 1};".to_owned();
+    }
+    let j = i - 1;
+    format!("let v{i} = {{a=v{j}.f {i}; f=fun x -> if x < {i} then v{j}.a else x}};")
+}
+
+// Many calls to the same polymorphic function
+fn polymorphic(i: usize) -> String {
+    if i == 0 {
+        return "let pair = fun (type t) (x: t): {a: t; b: t} -> {a=x; b=x};".to_owned();
+    }
+    if i.is_multiple_of(2) {
+        format!("let p{i} = (pair {i}).a + 1;")
+    } else {
+        format!("let p{i} = (pair \"s{i}\").b;")
+    }
+}
+
+// Variants and matches on them
+fn variants(i: usize) -> String {
+    format!("let m{i} = match (if {i} < 3 then `A {i} else `B {{x={i}}}) with | `A a -> a | `B r -> r.x + {i};")
+}
+
+// Mutable records whose fields are read and written by later definitions
+fn mutation(i: usize) -> String {
+    if i.is_multiple_of(4) {
+        format!("let r{i} = {{mut x={i}; mut y=\"s\"}};")
+    } else {
+        let j = i - i % 4;
+        format!("r{j}.x <- r{j}.x + {i}; let s{i} = r{j}.y;")
+    }
+}
+
+type Pattern = (&'static str, fn(usize) -> String, &'static [usize]);
+const PATTERNS: [Pattern; 5] = [
+    ("independent", independent, SIZES),
+    ("chain", chain, CHAIN_SIZES),
+    ("polymorphic", polymorphic, SIZES),
+    ("variants", variants, SIZES),
+    ("mutation", mutation, SIZES),
+];
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+
+    println!(
+        "{:>12} {:>8} {:>12} {:>12} {:>14}",
+        "pattern", "lines", "type nodes", "total", "per line"
+    );
+    for (name, gen_line, sizes) in PATTERNS {
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+
+        for &lines in sizes {
+            let src: Vec<_> = (0..lines).map(gen_line).collect();
+            let src = src.join("\n");
+
+            let mut state = State::new();
+            let start = Instant::now();
+            match state.process(&src) {
+                CompilationResult::Success(_) => {}
+                CompilationResult::Error(e) => panic!("{}", e),
+            }
+            let elapsed = start.elapsed();
+
+            let per_line = elapsed / lines as u32;
+            let nodes = state.num_type_nodes();
+            println!("{:>12} {:>8} {:>12} {:>12?} {:>14?}", name, lines, nodes, elapsed, per_line);
+        }
+    }
+}
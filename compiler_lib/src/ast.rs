@@ -19,6 +19,11 @@ pub enum Literal {
     Float,
     Int,
     Str,
+
+    // Fixed width integers
+    I32,
+    I64,
+    U8,
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +41,8 @@ pub enum Op {
 
     Eq,
     Neq,
+    PhysEq,
+    PhysNeq,
 }
 
 pub type OpType = (Option<Literal>, Literal);
@@ -44,27 +51,58 @@ pub const FLOAT_OP: OpType = (Some(Literal::Float), Literal::Float);
 pub const STR_OP: OpType = (Some(Literal::Str), Literal::Str);
 pub const INT_CMP: OpType = (Some(Literal::Int), Literal::Bool);
 pub const FLOAT_CMP: OpType = (Some(Literal::Float), Literal::Bool);
+pub const STR_CMP: OpType = (Some(Literal::Str), Literal::Bool);
 pub const ANY_CMP: OpType = (None, Literal::Bool);
+pub const I32_OP: OpType = (Some(Literal::I32), Literal::I32);
+pub const I64_OP: OpType = (Some(Literal::I64), Literal::I64);
+pub const U8_OP: OpType = (Some(Literal::U8), Literal::U8);
+pub const I32_CMP: OpType = (Some(Literal::I32), Literal::Bool);
+pub const I64_CMP: OpType = (Some(Literal::I64), Literal::Bool);
+pub const U8_CMP: OpType = (Some(Literal::U8), Literal::Bool);
 
 type LetDefinition = (LetPattern, Box<SExpr>);
 pub type LetRecDefinition = (StringId, SExpr);
 
 #[derive(Debug, Clone)]
 pub enum LetPattern {
+    Alias(Box<LetPattern>, Spanned<StringId>, Option<STypeExpr>),
     Case(Spanned<StringId>, Box<LetPattern>),
     Record(Spanned<(Vec<TypeParam>, Vec<(Spanned<StringId>, Box<LetPattern>)>)>),
     Var((Option<StringId>, Span), Option<STypeExpr>),
 }
+impl LetPattern {
+    /// The pattern inside any `as` aliases
+    pub fn without_aliases(&self) -> &LetPattern {
+        let mut pat = self;
+        while let LetPattern::Alias(sub_pat, ..) = pat {
+            pat = sub_pat;
+        }
+        pat
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TypeParam {
     pub name: Spanned<StringId>,
     pub alias: Spanned<StringId>,
+    // Optional bounds, written `type t <: upper :> lower`
+    pub upper: Option<STypeExpr>,
+    pub lower: Option<STypeExpr>,
 }
 impl TypeParam {
-    pub fn new(name: Spanned<StringId>, alias: Option<Spanned<StringId>>) -> Self {
+    pub fn new(
+        name: Spanned<StringId>,
+        alias: Option<Spanned<StringId>>,
+        upper: Option<STypeExpr>,
+        lower: Option<STypeExpr>,
+    ) -> Self {
         let alias = alias.unwrap_or(name);
-        Self { name, alias }
+        Self {
+            name,
+            alias,
+            upper,
+            lower,
+        }
     }
 }
 
@@ -103,6 +141,16 @@ pub enum TypeExpr {
 }
 pub type STypeExpr = Spanned<TypeExpr>;
 
+// `let* p1 = e1 and* p2 = e2 ...`, with each binding operator paired with its span
+pub type LetOpDefinition = Vec<(Spanned<StringId>, Spanned<LetPattern>, SExpr)>;
+
+// Statements in blocks may also be `let*` bindings, which take the rest of the block as a continuation
+#[derive(Debug, Clone)]
+pub enum BlockStatement {
+    LetOp(LetOpDefinition),
+    Statement(Statement),
+}
+
 #[derive(Debug, Clone)]
 pub enum Statement {
     Empty,
@@ -112,6 +160,9 @@ pub enum Statement {
     Println(Vec<SExpr>),
 }
 
+// `val name: type;` declarations in an interface file
+pub type InterfaceDecl = (Spanned<StringId>, STypeExpr);
+
 fn enumerate_tuple_fields<T, R>(
     vals: impl IntoIterator<Item = (T, Span)>,
     strings: &mut lasso::Rodeo,
@@ -156,6 +207,88 @@ pub fn make_tuple_type(mut vals: Vec<STypeExpr>, strings: &mut lasso::Rodeo) ->
     TypeExpr::Record(fields)
 }
 
+// List syntax is sugar for the variants `Cons (head, tail) and `Nil {}
+pub fn make_cons_expr(head: SExpr, tail: SExpr, span: Span, strings: &mut lasso::Rodeo) -> Expr {
+    let tag = strings.get_or_intern_static("Cons");
+    let pair = make_tuple_expr(vec![head, tail], strings);
+    expr::case((tag, span), Box::new((pair, span)))
+}
+
+pub fn make_list_expr(vals: Spanned<Vec<SExpr>>, strings: &mut lasso::Rodeo) -> Expr {
+    let (vals, span) = vals;
+    let nil = strings.get_or_intern_static("Nil");
+    let mut list = expr::case((nil, span), Box::new((expr::record(vec![]), span)));
+    for val in vals.into_iter().rev() {
+        list = make_cons_expr(val, (list, span), span, strings);
+    }
+    list
+}
+
+pub fn make_cons_pattern(
+    head: Spanned<LetPattern>,
+    tail: Spanned<LetPattern>,
+    span: Span,
+    strings: &mut lasso::Rodeo,
+) -> LetPattern {
+    let tag = strings.get_or_intern_static("Cons");
+    let pair = make_tuple_pattern((vec![head, tail], span), strings);
+    LetPattern::Case((tag, span), Box::new(pair))
+}
+
+pub fn make_list_pattern(vals: Spanned<Vec<Spanned<LetPattern>>>, strings: &mut lasso::Rodeo) -> LetPattern {
+    let (vals, span) = vals;
+    let nil = strings.get_or_intern_static("Nil");
+    let mut list = LetPattern::Case((nil, span), Box::new(LetPattern::Var((None, span), None)));
+    for val in vals.into_iter().rev() {
+        list = make_cons_pattern(val, (list, span), span, strings);
+    }
+    list
+}
+
+// let* x = a and* y = b in body desugars to (let*) ((and*) (a, b), fun (x, y) -> body)
+pub fn make_let_op_expr(defs: LetOpDefinition, body: SExpr, strings: &mut lasso::Rodeo) -> Expr {
+    let mut defs = defs.into_iter();
+    let (let_op, mut pat, mut arg) = defs.next().unwrap();
+    for ((and_op, span), pat2, arg2) in defs {
+        let func = (expr::variable(and_op), span);
+        let pair = make_tuple_expr(vec![arg, arg2], strings);
+        arg = (expr::call(Box::new(func), Box::new((pair, span)), false), span);
+        pat = (make_tuple_pattern((vec![pat, pat2], span), strings), span);
+    }
+
+    let (let_op, span) = let_op;
+    let body_span = body.1;
+    let lambda = (expr::func_def(None, pat, None, Box::new(body)), body_span);
+    let pair = make_tuple_expr(vec![arg, lambda], strings);
+    expr::call(Box::new((expr::variable(let_op), span)), Box::new((pair, span)), false)
+}
+
+// Each statement span covers from the start of the statement to the end of the block
+pub fn make_block_expr(stmts: Vec<(BlockStatement, Span)>, rest: SExpr, strings: &mut lasso::Rodeo) -> Expr {
+    fn finish(mut statements: Vec<Statement>, rest: SExpr, span: Span) -> SExpr {
+        if statements.is_empty() {
+            return rest;
+        }
+        statements.reverse();
+        (expr::block(statements, Box::new(rest)), span)
+    }
+
+    let mut rest = rest;
+    let mut rest_span = rest.1;
+    let mut statements = Vec::new();
+    for (stmt, span) in stmts.into_iter().rev() {
+        match stmt {
+            BlockStatement::LetOp(defs) => {
+                let body = finish(std::mem::take(&mut statements), rest, rest_span);
+                rest = (make_let_op_expr(defs, body, strings), span);
+            }
+            BlockStatement::Statement(stmt) => statements.push(stmt),
+        }
+        rest_span = span;
+    }
+    finish(statements, rest, rest_span).0
+}
+
 pub fn make_join_ast(kind: JoinKind, mut children: Vec<STypeExpr>) -> TypeExpr {
     if children.len() <= 1 {
         children.pop().unwrap().0
@@ -114,6 +114,14 @@ impl BoundPairsSet {
         }
     }
 
+    /// All pairs in the set, in unspecified order
+    pub fn pairs(&self) -> Vec<(SourceLoc, SourceLoc)> {
+        let Some(sub) = self.0.as_ref() else {
+            return Vec::new();
+        };
+        sub.0.iter().map(|(&l, &r)| if self.1 { (r, l) } else { (l, r) }).collect()
+    }
+
     // Return true if there's any (loc, loc2) in self such that (loc, name) is in lhs and (loc2, name) is in rhs
     pub fn disjoint_union_vars_have_match<'a>(&self, mut lhs: &'a HashSet<VarSpec>, mut rhs: &'a HashSet<VarSpec>) -> bool {
         if self.1 {
@@ -1,8 +1,10 @@
+use std::collections::HashSet;
 use std::mem::swap;
 
 use crate::ast;
 use crate::ast::StringId;
 use crate::js;
+use crate::spans::Span;
 use crate::unwindmap::UnwindMap;
 
 pub struct ModuleBuilder {
@@ -25,6 +27,11 @@ impl ModuleBuilder {
         }
     }
 
+    /// Bind an ML variable to a function provided by the JS runtime.
+    pub fn add_builtin_var(&mut self, ml_name: StringId, js_name: &str) {
+        self.set_binding(ml_name, js::var(js_name.to_string()));
+    }
+
     fn set_binding(&mut self, k: StringId, v: js::Expr) {
         self.bindings.insert(k, v);
     }
@@ -78,7 +85,8 @@ impl ModuleBuilder {
         js_name
     }
 }
-pub struct Context<'a>(pub &'a mut ModuleBuilder, pub &'a lasso::Rodeo);
+/// The spans are the print arguments that need converting from i32 or u8 Numbers to print as integers
+pub struct Context<'a>(pub &'a mut ModuleBuilder, pub &'a lasso::Rodeo, pub &'a HashSet<Span>);
 impl<'a> Context<'a> {
     fn ml_scope<T>(&mut self, cb: impl FnOnce(&mut Self) -> T) -> T {
         let n = self.bindings.unwind_point();
@@ -127,6 +135,13 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
         ast::Expr::BinOp(e) => {
             let lhs = compile(ctx, &e.lhs);
             let rhs = compile(ctx, &e.rhs);
+            if let (ast::Literal::I32, ast::Op::Mult) = (&e.op_type.1, &e.op) {
+                let imul = js::field(js::var("Math".to_string()), "imul".to_string());
+                return js::call_multi(imul, vec![lhs, rhs]);
+            }
+
+            // JS operators work on both BigInts and Numbers, so overloaded int/float operators
+            // need no special handling as long as both operands have the same type.
             let jsop = match e.op {
                 ast::Op::Add => js::Op::Add,
                 ast::Op::Sub => js::Op::Sub,
@@ -139,10 +154,29 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
                 ast::Op::Gt => js::Op::Gt,
                 ast::Op::Gte => js::Op::Gte,
 
-                ast::Op::Eq => js::Op::Eq,
-                ast::Op::Neq => js::Op::Neq,
+                // Structural equality is implemented by runtime functions
+                ast::Op::Eq => return js::call_multi(js::var("eq".to_string()), vec![lhs, rhs]),
+                ast::Op::Neq => return js::call_multi(js::var("neq".to_string()), vec![lhs, rhs]),
+                ast::Op::PhysEq => js::Op::Eq,
+                ast::Op::PhysNeq => js::Op::Neq,
             };
-            js::binop(lhs, rhs, jsop)
+            let mut res = js::binop(lhs, rhs, jsop);
+
+            // Fixed width integer arithmetic wraps around on overflow. i32 and u8 are represented
+            // as Numbers for speed, and i64 as BigInts, the same as int.
+            if let (ast::Literal::I32 | ast::Literal::U8, ast::Op::Div) = (&e.op_type.1, &e.op) {
+                let trunc = js::field(js::var("Math".to_string()), "trunc".to_string());
+                res = js::call_multi(trunc, vec![res]);
+            }
+            match e.op_type.1 {
+                ast::Literal::I32 => js::binop(res, js::lit("0".to_string()), js::Op::BitOr),
+                ast::Literal::U8 => js::binop(res, js::lit("255".to_string()), js::Op::BitAnd),
+                ast::Literal::I64 => {
+                    let as_int_n = js::field(js::var("BigInt".to_string()), "asIntN".to_string());
+                    js::call_multi(as_int_n, vec![js::lit("64".to_string()), res])
+                }
+                _ => res,
+            }
         }
         ast::Expr::Block(e) => {
             ctx.ml_scope(|ctx| {
@@ -198,8 +232,18 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
                 swap(&mut new_scope_name, &mut ctx.scope_var_name);
 
                 //////////////////////////////////////////////////////
-                let js_pattern = compile_let_pattern(ctx, &e.param.0).unwrap_or_else(|| js::var("_".to_string()));
-                let body = compile(ctx, &e.body);
+                let (js_pattern, body) = if pattern_has_alias(&e.param.0) {
+                    // JS destructuring can't bind a value and its parts at the same time,
+                    // so take a plain parameter and destructure it in the function body instead.
+                    let js_arg = js::var(ctx.new_param_name());
+                    let mut exprs = Vec::new();
+                    compile_let_pattern_flat(ctx, &mut exprs, &e.param.0, js_arg.clone());
+                    exprs.push(compile(ctx, &e.body));
+                    (js_arg, js::comma_list(exprs))
+                } else {
+                    let js_pattern = compile_let_pattern(ctx, &e.param.0).unwrap_or_else(|| js::var("_".to_string()));
+                    (js_pattern, compile(ctx, &e.body))
+                };
                 //////////////////////////////////////////////////////
 
                 swap(&mut new_scope_name, &mut ctx.scope_var_name);
@@ -216,9 +260,13 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
         ast::Expr::InstantiateUni(e) => compile(ctx, &e.expr),
         ast::Expr::Literal(e) => {
             let mut code = e.value.0.clone();
-            if let ast::Literal::Int = e.lit_type {
+            if let ast::Literal::Int | ast::Literal::I64 = e.lit_type {
                 code.push_str("n");
             }
+            // i32 and u8 are Numbers, where -0 is distinct from 0
+            if code == "-0" && matches!(e.lit_type, ast::Literal::I32 | ast::Literal::U8) {
+                code.remove(0);
+            }
             if code.starts_with("-") {
                 js::unary_minus(js::lit(code[1..].to_string()))
             } else {
@@ -237,17 +285,16 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
             let temp_var = ctx.new_temp_var_assign(match_compiled, &mut exprs);
 
             let tag_expr = js::field(temp_var.clone(), "$tag".to_string());
-            let val_expr = js::field(temp_var.clone(), "$val".to_string());
 
             let mut branches = Vec::new();
             let mut wildcard = None;
             for ((pattern, _), rhs_expr) in &e.cases {
                 use ast::LetPattern::*;
-                match pattern {
-                    Case((tag, _), sub_pattern) => {
+                match pattern.without_aliases() {
+                    Case((tag, _), _) => {
                         ctx.ml_scope(|ctx| {
                             let mut exprs = Vec::new();
-                            compile_let_pattern_flat(ctx, &mut exprs, sub_pattern, val_expr.clone());
+                            compile_let_pattern_flat(ctx, &mut exprs, pattern, temp_var.clone());
                             exprs.push(compile(ctx, rhs_expr));
                             branches.push((ctx.get(*tag), js::comma_list(exprs)));
                         });
@@ -287,6 +334,11 @@ fn compile(ctx: &mut Context<'_>, expr: &ast::SExpr) -> js::Expr {
 fn compile_let_pattern_flat(ctx: &mut Context<'_>, out: &mut Vec<js::Expr>, pat: &ast::LetPattern, rhs: js::Expr) {
     use ast::LetPattern::*;
     match pat {
+        Alias(sub_pat, (ml_name, _), _) => {
+            // Bind the alias first and then destructure the bound value so rhs is only evaluated once
+            let lhs = ctx.new_var_assign(*ml_name, rhs, out);
+            compile_let_pattern_flat(ctx, out, sub_pat, lhs);
+        }
         Case(_, val_pat) => {
             // rhs.$val
             let rhs = js::field(rhs, "$val".to_string());
@@ -311,9 +363,20 @@ fn compile_let_pattern_flat(ctx: &mut Context<'_>, out: &mut Vec<js::Expr>, pat:
     }
 }
 
+fn pattern_has_alias(pat: &ast::LetPattern) -> bool {
+    use ast::LetPattern::*;
+    match pat {
+        Alias(..) => true,
+        Case(_, val_pat) => pattern_has_alias(val_pat),
+        Record(((_, pairs), _)) => pairs.iter().any(|(_, pat)| pattern_has_alias(pat)),
+        Var(..) => false,
+    }
+}
+
 fn compile_let_pattern(ctx: &mut Context<'_>, pat: &ast::LetPattern) -> Option<js::Expr> {
     use ast::LetPattern::*;
     Some(match pat {
+        Alias(..) => unreachable!(),
         Case(_, val_pat) => js::obj(vec![("$val".to_string(), compile_let_pattern(ctx, &*val_pat)?)]),
         Record(((_, pairs), _)) => js::obj(
             pairs
@@ -358,7 +421,17 @@ fn compile_statement(ctx: &mut Context<'_>, exprs: &mut Vec<js::Expr>, stmt: &as
             }
         }
         Println(args) => {
-            let args = args.iter().map(|expr| compile(ctx, expr)).collect();
+            let args = args
+                .iter()
+                .map(|expr| {
+                    let arg = compile(ctx, expr);
+                    if ctx.2.contains(&expr.1) {
+                        js::call(js::var("BigInt".to_string()), arg)
+                    } else {
+                        arg
+                    }
+                })
+                .collect();
             exprs.push(js::println(args));
         }
     }
@@ -21,13 +21,15 @@ use crate::spans::SpannedError as TypeError;
 use crate::type_errors::HoleSrc;
 use crate::type_errors::PartialTypeError;
 use crate::type_errors::immutable_field_err;
+use crate::type_errors::limit_exceeded_err;
+use crate::type_errors::missing_bound_err;
 use crate::type_errors::missing_field_err;
 use crate::type_errors::poisoned_poly_err;
 use crate::type_errors::type_escape_error;
 use crate::type_errors::type_mismatch_err;
 use crate::type_errors::unhandled_variant_err;
 
-const NONE: TypeNodeInd = TypeNodeInd(usize::MAX);
+pub const NONE: TypeNodeInd = TypeNodeInd(usize::MAX);
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Value(pub TypeNodeInd);
@@ -45,6 +47,10 @@ pub struct TypeCtor {
     pub name: StringId,
     pub span: Option<Span>, // None for builtin type ctors
     pub scopelvl: ScopeLvl,
+    // Bounds of abstract types created from bounded type parameters. Values of the type
+    // can be used as the upper bound, and values of the lower bound can be used as the type.
+    pub upper: Option<Value>,
+    pub lower: Option<Use>,
     // debug: String,
 }
 impl TypeCtor {
@@ -54,6 +60,8 @@ impl TypeCtor {
             name,
             span,
             scopelvl,
+            upper: None,
+            lower: None,
             // debug,
         }
     }
@@ -69,6 +77,30 @@ pub struct PolyHeadData {
     pub params: Box<[(StringId, Span)]>,
 }
 
+/// Bounds declared for a type parameter of a polymorphic type, e.g. `type t <: {x: int}`.
+/// Only parameters with at least one bound are listed.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamBounds {
+    pub name: StringId,
+    pub upper: Option<(Value, Use)>,
+    pub lower: Option<(Value, Use)>,
+}
+impl ParamBounds {
+    fn visit_inds(&mut self, f: &mut dyn FnMut(ChildRole, &mut TypeNodeInd)) {
+        for (upper, bound) in [(true, &mut self.upper), (false, &mut self.lower)] {
+            if let Some((v, u)) = bound {
+                f(ChildRole::Bound(self.name, upper, false), &mut v.0);
+                f(ChildRole::Bound(self.name, upper, true), &mut u.0);
+            }
+        }
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = (TypeNodeInd, bool)> + '_ {
+        let pairs = self.upper.iter().chain(self.lower.iter());
+        pairs.flat_map(|(v, u)| [(v.0, true), (u.0, false)])
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct VarSpec {
     pub loc: SourceLoc,
@@ -101,7 +133,7 @@ pub enum VTypeHead {
         ty: TypeCtorInd,
     },
 
-    VPolyHead(Rc<PolyHeadData>, Value, bool),
+    VPolyHead(Rc<PolyHeadData>, Value, bool, Box<[ParamBounds]>),
     VTypeVar(VarSpec),
     VDisjointIntersect(HashSet<VarSpec>, Option<Value>),
 }
@@ -131,7 +163,11 @@ pub enum UTypeHead {
     UAbstract {
         ty: TypeCtorInd,
     },
-    UPolyHead(Rc<PolyHeadData>, Use, bool),
+    // Used for overloaded operators. The flows for whichever case matches the incoming abstract type are added.
+    UOverload {
+        cases: Vec<(TypeCtorInd, Vec<(Value, Use)>)>,
+    },
+    UPolyHead(Rc<PolyHeadData>, Use, bool, Box<[ParamBounds]>),
     UTypeVar(VarSpec),
     UDisjointUnion(HashSet<VarSpec>, Option<Use>),
 }
@@ -143,8 +179,12 @@ enum CheckHeadsResult {
     Instantiate {
         poly: Rc<PolyHeadData>,
         substitution_params: Rc<RefCell<HashMap<StringId, (Value, Use)>>>,
+        bounds: Box<[ParamBounds]>,
+        // The instantiation node that substitution_params belongs to
+        instantiation_node: TypeNodeInd,
         src_template: (Span, InstantiateSourceKind),
         reason: FlowReason,
+        inst_depth: usize,
 
         // If poly.kind is Universal, instantiate lhs then flow lhs' -> rhs
         // otherwise, instantiate rhs then flow lhs -> rhs'
@@ -232,20 +272,23 @@ fn check_heads(
         src_template,
     } = &lhs.0
     {
-        if let &UPolyHead(ref poly, rhs_sub, poison) = &rhs.0 {
-            if poly.kind == PolyKind::Existential {
-                if poison {
-                    return Err(poisoned_poly_err(lhs.1));
-                }
-                return Ok(CheckHeadsResult::Instantiate {
-                    poly: poly.clone(),
-                    substitution_params: params.clone(),
-                    src_template,
-                    reason: edge_context.reason,
-                    lhs_sub: target,
-                    rhs_sub,
-                });
+        if let &UPolyHead(ref poly, rhs_sub, poison, ref bounds) = &rhs.0
+            && poly.kind == PolyKind::Existential
+        {
+            if poison {
+                return Err(poisoned_poly_err(lhs.1));
             }
+            return Ok(CheckHeadsResult::Instantiate {
+                poly: poly.clone(),
+                substitution_params: params.clone(),
+                bounds: bounds.clone(),
+                instantiation_node: lhs_ind.0,
+                src_template,
+                reason: edge_context.reason,
+                inst_depth: edge_context.inst_depth,
+                lhs_sub: target,
+                rhs_sub,
+            });
         }
         out.push((target, rhs_ind, edge_context));
         return Ok(Done);
@@ -255,20 +298,23 @@ fn check_heads(
         src_template,
     } = &rhs.0
     {
-        if let &VPolyHead(ref poly, lhs_sub, poison) = &lhs.0 {
-            if poly.kind == PolyKind::Universal {
-                if poison {
-                    return Err(poisoned_poly_err(rhs.1));
-                }
-                return Ok(CheckHeadsResult::Instantiate {
-                    poly: poly.clone(),
-                    substitution_params: params.clone(),
-                    src_template,
-                    reason: edge_context.reason,
-                    lhs_sub,
-                    rhs_sub: target,
-                });
+        if let &VPolyHead(ref poly, lhs_sub, poison, ref bounds) = &lhs.0
+            && poly.kind == PolyKind::Universal
+        {
+            if poison {
+                return Err(poisoned_poly_err(rhs.1));
             }
+            return Ok(CheckHeadsResult::Instantiate {
+                poly: poly.clone(),
+                substitution_params: params.clone(),
+                bounds: bounds.clone(),
+                instantiation_node: rhs_ind.0,
+                src_template,
+                reason: edge_context.reason,
+                inst_depth: edge_context.inst_depth,
+                lhs_sub,
+                rhs_sub: target,
+            });
         }
         out.push((lhs_ind, target, edge_context));
         return Ok(Done);
@@ -276,14 +322,22 @@ fn check_heads(
 
     match (&lhs.0, &rhs.0) {
         // Check for polymorphic heads and update the edge context, then recurse
-        (&VPolyHead(ref lhs_poly, lhs_t, _), &UPolyHead(ref rhs_poly, rhs_t, _)) => {
+        (&VPolyHead(ref lhs_poly, lhs_t, _, ref lhs_bounds), &UPolyHead(ref rhs_poly, rhs_t, _, ref rhs_bounds)) => {
+            check_param_bounds(
+                strings,
+                lhs_poly.kind,
+                (lhs_bounds, lhs.1),
+                (rhs_bounds, rhs.1),
+                &edge_context,
+                out,
+            )?;
             edge_context.bound_pairs.push((lhs_poly.loc, rhs_poly.loc));
             out.push((lhs_t, rhs_t, edge_context));
         }
-        (&VPolyHead(ref lhs_poly, lhs_t, _), _) => {
+        (&VPolyHead(ref lhs_poly, lhs_t, ..), _) => {
             out.push((lhs_t, rhs_ind, edge_context));
         }
-        (_, &UPolyHead(ref rhs_poly, rhs_t, _)) => {
+        (_, &UPolyHead(ref rhs_poly, rhs_t, ..)) => {
             out.push((lhs_ind, rhs_t, edge_context));
         }
 
@@ -344,6 +398,20 @@ fn check_heads(
                 if edge_context.scopelvl < ty_def1.scopelvl {
                     return Err(type_escape_error(strings, ty_def1, lhs, rhs, edge_context.scopelvl));
                 }
+            } else if let Some((lhs2, rhs2)) = abstract_bound_redirect(type_ctors, lhs_ind, lhs, rhs_ind, rhs) {
+                out.push((lhs2, rhs2, edge_context));
+            } else {
+                return Err(type_mismatch_err(strings, type_ctors, lhs, rhs));
+            }
+        }
+
+        (&VAbstract { ty }, UOverload { cases }) => {
+            if let Some((_, flows)) = cases.iter().find(|&&(ty2, _)| ty2 == ty) {
+                for &(v, u) in flows {
+                    out.push((v, u, edge_context.clone()));
+                }
+            } else if let Some((lhs2, rhs2)) = abstract_bound_redirect(type_ctors, lhs_ind, lhs, rhs_ind, rhs) {
+                out.push((lhs2, rhs2, edge_context));
             } else {
                 return Err(type_mismatch_err(strings, type_ctors, lhs, rhs));
             }
@@ -356,12 +424,85 @@ fn check_heads(
         }
 
         _ => {
-            return Err(type_mismatch_err(strings, type_ctors, lhs, rhs));
+            if let Some((lhs2, rhs2)) = abstract_bound_redirect(type_ctors, lhs_ind, lhs, rhs_ind, rhs) {
+                out.push((lhs2, rhs2, edge_context));
+            } else {
+                return Err(type_mismatch_err(strings, type_ctors, lhs, rhs));
+            }
         }
     };
     Ok(Done)
 }
 
+/// When a value of an abstract type with an upper bound fails to match, check its bound instead.
+/// Likewise, values that don't match an abstract type with a lower bound are checked against the bound.
+fn abstract_bound_redirect(
+    type_ctors: &[TypeCtor],
+    lhs_ind: Value,
+    lhs: &VTypeNode,
+    rhs_ind: Use,
+    rhs: &UTypeNode,
+) -> Option<(Value, Use)> {
+    if let VTypeHead::VAbstract { ty } = lhs.0
+        && let Some(upper) = type_ctors[ty.0].upper
+    {
+        return Some((upper, rhs_ind));
+    }
+    if let UTypeHead::UAbstract { ty } = rhs.0
+        && let Some(lower) = type_ctors[ty.0].lower
+    {
+        return Some((lhs_ind, lower));
+    }
+    None
+}
+
+/// When comparing two polymorphic types, every type parameter that satisfies the bounds of the
+/// more general side has to satisfy the bounds of the other side. For universal types, this is
+/// the use side, while for existential types it is the value side.
+fn check_param_bounds(
+    strings: &lasso::Rodeo,
+    kind: PolyKind,
+    lhs: (&[ParamBounds], Span),
+    rhs: (&[ParamBounds], Span),
+    edge_context: &TypeEdge,
+    out: &mut Vec<(Value, Use, TypeEdge)>,
+) -> Result<(), PartialTypeError> {
+    // Bounds never refer to the type parameters they constrain, so there are no bound pairs to track.
+    let mut ctx = edge_context.clone();
+    ctx.bound_pairs = BoundPairsSet::default();
+
+    let ((general, general_span), (specific, specific_span)) = match kind {
+        PolyKind::Universal => (rhs, lhs),
+        PolyKind::Existential => (lhs, rhs),
+    };
+    for b in specific {
+        let other = general.iter().find(|b2| b2.name == b.name);
+        if let Some((_, upper)) = b.upper {
+            let Some((upper2, _)) = other.and_then(|b2| b2.upper) else {
+                return Err(missing_bound_err(
+                    strings.resolve(&b.name),
+                    "an upper",
+                    specific_span,
+                    general_span,
+                ));
+            };
+            out.push((upper2, upper, ctx.clone()));
+        }
+        if let Some((lower, _)) = b.lower {
+            let Some((_, lower2)) = other.and_then(|b2| b2.lower) else {
+                return Err(missing_bound_err(
+                    strings.resolve(&b.name),
+                    "a lower",
+                    specific_span,
+                    general_span,
+                ));
+            };
+            out.push((lower, lower2, ctx.clone()));
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct InferenceVarData {
     pub scopelvl: ScopeLvl,
@@ -399,6 +540,131 @@ impl ExtNodeDataTrait for TypeNode {
                 .retain(|_, (v, u)| (v.0 < i || v.0 == NONE) && (u.0 < i || u.0 == NONE));
         }
     }
+
+    fn renumber(&mut self, f: &dyn Fn(TypeNodeInd) -> TypeNodeInd) {
+        self.visit_inds(&mut |_, i| *i = f(*i));
+    }
+}
+impl TypeNode {
+    /// Call the given function on every node index referenced by this node's head, along with its role in the head.
+    /// Each params map is owned by a single node, so it is visited exactly once.
+    fn visit_inds(&mut self, f: &mut dyn FnMut(ChildRole, &mut TypeNodeInd)) {
+        use ChildRole::*;
+        let params_inds = |f: &mut dyn FnMut(ChildRole, &mut TypeNodeInd),
+                           params: &RefCell<HashMap<StringId, (Value, Use)>>| {
+            for (name, (v, u)) in params.borrow_mut().iter_mut() {
+                f(Param(*name, false), &mut v.0);
+                f(Param(*name, true), &mut u.0);
+            }
+        };
+
+        match self {
+            TypeNode::Var(_) | TypeNode::Placeholder => {}
+            TypeNode::Value((head, ..)) => {
+                use VTypeHead::*;
+                match head {
+                    VUnion(vals) => vals.iter_mut().for_each(|v| f(Member, &mut v.0)),
+                    VInstantiateExist { params, target, .. } => {
+                        f(Target, &mut target.0);
+                        params_inds(f, params);
+                    }
+                    VFunc { arg, ret } => {
+                        f(Arg, &mut arg.0);
+                        f(Ret, &mut ret.0);
+                    }
+                    VObj { fields } => {
+                        for (name, (r, w, _)) in fields.iter_mut() {
+                            f(Field(*name), &mut r.0);
+                            if let Some(w) = w {
+                                f(FieldWrite(*name), &mut w.0);
+                            }
+                        }
+                    }
+                    VCase { case: (tag, val) } => f(Tag(*tag), &mut val.0),
+                    VPolyHead(_, val, _, bounds) => {
+                        f(Body, &mut val.0);
+                        bounds.iter_mut().for_each(|b| b.visit_inds(f));
+                    }
+                    VDisjointIntersect(_, def) => def.iter_mut().for_each(|v| f(Default, &mut v.0)),
+                    VTop | VAbstract { .. } | VTypeVar(..) => {}
+                }
+            }
+            TypeNode::Use((head, ..)) => {
+                use UTypeHead::*;
+                match head {
+                    UIntersection(uses) => uses.iter_mut().for_each(|u| f(Member, &mut u.0)),
+                    UInstantiateUni { params, target, .. } => {
+                        f(Target, &mut target.0);
+                        params_inds(f, params);
+                    }
+                    UFunc { arg, ret } => {
+                        f(Arg, &mut arg.0);
+                        f(Ret, &mut ret.0);
+                    }
+                    UObj { fields } => {
+                        for (name, (r, w, _)) in fields.iter_mut() {
+                            f(Field(*name), &mut r.0);
+                            if let Some(w) = w {
+                                f(FieldWrite(*name), &mut w.0);
+                            }
+                        }
+                    }
+                    UCase { cases, wildcard } => {
+                        cases.iter_mut().for_each(|(tag, u)| f(Tag(*tag), &mut u.0));
+                        wildcard.iter_mut().for_each(|u| f(Wildcard, &mut u.0));
+                    }
+                    UOverload { cases } => {
+                        for (i, (_, flows)) in cases.iter_mut().enumerate() {
+                            for (v, u) in flows.iter_mut() {
+                                f(OverloadCase(i, false), &mut v.0);
+                                f(OverloadCase(i, true), &mut u.0);
+                            }
+                        }
+                    }
+                    UPolyHead(_, u, _, bounds) => {
+                        f(Body, &mut u.0);
+                        bounds.iter_mut().for_each(|b| b.visit_inds(f));
+                    }
+                    UDisjointUnion(_, def) => def.iter_mut().for_each(|u| f(Default, &mut u.0)),
+                    UBot | UAbstract { .. } | UTypeVar(..) => {}
+                }
+            }
+        }
+    }
+
+    /// Returns the node indices referenced by this node's head, labelled with their role in the head.
+    pub fn children(&self) -> Vec<(ChildRole, TypeNodeInd)> {
+        // visit_inds needs mutable access, so visit a copy of the head instead
+        let mut copy = match self {
+            TypeNode::Value((head, span, _)) => TypeNode::Value((head.clone(), *span, PolyDeps::default())),
+            TypeNode::Use((head, span, _)) => TypeNode::Use((head.clone(), *span, PolyDeps::default())),
+            TypeNode::Var(_) | TypeNode::Placeholder => return Vec::new(),
+        };
+        let mut out = Vec::new();
+        copy.visit_inds(&mut |role, i| out.push((role, *i)));
+        out
+    }
+}
+
+/// The role of a node index within the head that references it, e.g. a function's argument or a record field.
+#[derive(Debug, Clone, Copy)]
+pub enum ChildRole {
+    Member,
+    Target,
+    /// Type parameter name, and whether this is the use half of the pair
+    Param(StringId, bool),
+    Arg,
+    Ret,
+    Field(StringId),
+    FieldWrite(StringId),
+    Tag(StringId),
+    Wildcard,
+    /// Index of the overload case, and whether this is the use half of the pair
+    OverloadCase(usize, bool),
+    Body,
+    /// Type parameter name, whether this is the upper bound, and whether this is the use half of the pair
+    Bound(StringId, bool, bool),
+    Default,
 }
 
 /// Used to track the reason a flow edge was added so we can backtrack when printing errors
@@ -411,9 +677,11 @@ pub enum FlowReason {
 
 #[derive(Debug, Clone)]
 pub struct TypeEdge {
-    scopelvl: ScopeLvl,
-    bound_pairs: BoundPairsSet,
+    pub scopelvl: ScopeLvl,
+    pub bound_pairs: BoundPairsSet,
     pub reason: FlowReason,
+    // Number of nested instantiations that led to this edge being added
+    inst_depth: usize,
 }
 impl TypeEdge {
     fn flip(&self) -> Self {
@@ -441,6 +709,35 @@ impl EdgeDataTrait<TypeNode> for TypeEdge {
 
         changed
     }
+
+    fn renumber(&mut self, f: &dyn Fn(TypeNodeInd) -> TypeNodeInd) {
+        self.reason = match self.reason {
+            FlowReason::Root(span) => FlowReason::Root(span),
+            FlowReason::Transitivity(h) => FlowReason::Transitivity(f(h)),
+            FlowReason::Check(v, u) => FlowReason::Check(Value(f(v.0)), Use(f(u.0))),
+        };
+    }
+}
+
+/// Limits on the work done by the type checker, so that pathological programs produce
+/// an error rather than running out of time or memory. The defaults are unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum total number of nodes in the type graph
+    pub max_type_nodes: usize,
+    /// Maximum number of edges and type pairs processed by a single flow
+    pub max_flow_work: usize,
+    /// Maximum number of nested instantiations of polymorphic types within a single flow
+    pub max_instantiation_depth: usize,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_type_nodes: usize::MAX,
+            max_flow_work: usize::MAX,
+            max_instantiation_depth: usize::MAX,
+        }
+    }
 }
 
 pub struct TypeCheckerCore {
@@ -449,6 +746,11 @@ pub struct TypeCheckerCore {
     pub type_ctors: Vec<TypeCtor>,
     pub flowcount: u32,
     pub varcount: u32,
+    pub limits: Limits,
+    // Whether type errors should explain every step of how the value reached the use rejecting it
+    pub verbose_errors: bool,
+    // Number of type constructors at each (possibly nested) save
+    saved_type_ctors: Vec<usize>,
 }
 impl TypeCheckerCore {
     pub fn new() -> Self {
@@ -457,6 +759,9 @@ impl TypeCheckerCore {
             type_ctors: Vec::new(),
             flowcount: 0,
             varcount: 0,
+            limits: Limits::default(),
+            verbose_errors: false,
+            saved_type_ctors: Vec::new(),
         }
     }
 
@@ -468,17 +773,38 @@ impl TypeCheckerCore {
     pub fn add_builtin_type(&mut self, name: StringId) -> TypeCtorInd {
         self.add_type_ctor(TypeCtor::new(name, None, ScopeLvl(0)))
     }
-    pub fn add_abstract_type(&mut self, name: StringId, span: Span, scopelvl: ScopeLvl) -> TypeCtorInd {
+    pub fn add_abstract_type(
+        &mut self,
+        name: StringId,
+        span: Span,
+        scopelvl: ScopeLvl,
+        upper: Option<Value>,
+        lower: Option<Use>,
+    ) -> TypeCtorInd {
         // println!("new abs ctor {} {}", name.into_inner(), self.type_ctors.len());
-        self.add_type_ctor(TypeCtor::new(name, Some(span), scopelvl))
+        let mut ctor = TypeCtor::new(name, Some(span), scopelvl);
+        ctor.upper = upper;
+        ctor.lower = lower;
+        self.add_type_ctor(ctor)
     }
 
-    fn new_edge_context(&self, reason: FlowReason, scopelvl: ScopeLvl) -> TypeEdge {
+    fn new_edge_context(&self, reason: FlowReason, scopelvl: ScopeLvl, inst_depth: usize) -> TypeEdge {
         TypeEdge {
             scopelvl,
             bound_pairs: BoundPairsSet::default(),
             reason,
+            inst_depth,
+        }
+    }
+
+    fn check_limits(&self, work: usize, span: Span) -> Result<(), TypeError> {
+        if self.r.len() > self.limits.max_type_nodes {
+            return Err(limit_exceeded_err("type graph nodes", self.limits.max_type_nodes, span));
+        }
+        if work > self.limits.max_flow_work {
+            return Err(limit_exceeded_err("flow work items", self.limits.max_flow_work, span));
         }
+        Ok(())
     }
 
     pub fn flow(
@@ -492,9 +818,13 @@ impl TypeCheckerCore {
         self.flowcount += 1;
         // println!("flow #{}: {}->{}", self.flowcount, lhs.0.0, rhs.0.0);
 
-        let mut pending_edges = vec![(lhs, rhs, self.new_edge_context(FlowReason::Root(expl_span), scopelvl))];
+        let mut pending_edges = vec![(lhs, rhs, self.new_edge_context(FlowReason::Root(expl_span), scopelvl, 0))];
         let mut type_pairs_to_check = Vec::new();
+        let mut work = 0;
         while let Some((lhs, rhs, edge_context)) = pending_edges.pop() {
+            work += 1;
+            self.check_limits(work, expl_span)?;
+
             // Check for top/bottom types
             if lhs.0 == NONE || rhs.0 == NONE {
                 continue;
@@ -504,6 +834,8 @@ impl TypeCheckerCore {
 
             // Check if adding that edge resulted in any new type pairs needing to be checked
             while let Some((lhs, rhs, edge_context)) = type_pairs_to_check.pop() {
+                work += 1;
+                self.check_limits(work, expl_span)?;
                 if let TypeNode::Value(lhs_head) = self.r.get(lhs).unwrap() {
                     if let TypeNode::Use(rhs_head) = self.r.get(rhs).unwrap() {
                         let lhs = Value(lhs);
@@ -523,13 +855,16 @@ impl TypeCheckerCore {
                             Ok(v) => v,
                             Err(mut e) => {
                                 e.add_hole_int(self, strings, (lhs, rhs));
+                                if self.verbose_errors {
+                                    e.add_flow_explanation(self, strings, (lhs, rhs));
+                                }
                                 return e.into();
                             }
                         };
 
                         // Handle any followup operations that require mutation
                         // e.g. function instantation
-                        self.flow_sub_mut(res, &mut pending_edges, scopelvl);
+                        self.flow_sub_mut(res, &mut pending_edges, scopelvl, expl_span)?;
                     }
                 }
             }
@@ -538,31 +873,59 @@ impl TypeCheckerCore {
         Ok(())
     }
 
-    fn flow_sub_mut(&mut self, res: CheckHeadsResult, out: &mut Vec<(Value, Use, TypeEdge)>, scopelvl: ScopeLvl) {
+    fn flow_sub_mut(
+        &mut self,
+        res: CheckHeadsResult,
+        out: &mut Vec<(Value, Use, TypeEdge)>,
+        scopelvl: ScopeLvl,
+        expl_span: Span,
+    ) -> Result<(), TypeError> {
         match res {
             CheckHeadsResult::Done => {}
             CheckHeadsResult::Instantiate {
                 poly,
                 substitution_params,
+                bounds,
+                instantiation_node,
                 src_template,
                 reason,
+                inst_depth,
                 lhs_sub,
                 rhs_sub,
             } => {
+                let inst_depth = inst_depth + 1;
+                if inst_depth > self.limits.max_instantiation_depth {
+                    let limit = self.limits.max_instantiation_depth;
+                    return Err(limit_exceeded_err("nested instantiations", limit, expl_span));
+                }
+
                 // Domain expansion - for type parameters not already specified, substitute them
                 // with a new inference variable. The same inference variable will be used for
                 // all instantiations of that parameter with the same instantiation node.
                 let mut params_mut = substitution_params.borrow_mut();
+                let mut modified = false;
                 for (name, _) in poly.params.iter().copied() {
                     // println!("inserting var for {}", name.into_inner());
-                    params_mut
-                        .entry(name)
-                        .or_insert_with(|| self.var(HoleSrc::Instantiation(src_template, name), scopelvl));
+                    params_mut.entry(name).or_insert_with(|| {
+                        modified = true;
+                        self.var(HoleSrc::Instantiation(src_template, name), scopelvl)
+                    });
                 }
                 drop(params_mut);
+                if modified {
+                    self.r.mark_data_modified(instantiation_node);
+                }
 
-                // Now do the actual instantiation
+                // The types substituted for bounded parameters have to satisfy the bounds
                 let params = substitution_params.borrow();
+                for b in bounds.iter() {
+                    let (v, u) = params[&b.name];
+                    let ctx = self.new_edge_context(reason, scopelvl, inst_depth);
+                    out.extend(b.upper.map(|(_, upper)| (v, upper, ctx.clone())));
+                    out.extend(b.lower.map(|(lower, _)| (lower, u, ctx)));
+                }
+
+                // Now do the actual instantiation
                 let mut ctx = InstantionContext::new(self, Substitutions::Type(&params), poly.loc);
 
                 // Functions can only be instantiated when they have no free variables,
@@ -571,15 +934,16 @@ impl TypeCheckerCore {
                     PolyKind::Universal => {
                         let new = ctx.instantiate_val(lhs_sub);
                         // println!("instantiate {}->{}", lhs_sub.0.0, new.0.0);
-                        out.push((new, rhs_sub, self.new_edge_context(reason, scopelvl)));
+                        out.push((new, rhs_sub, self.new_edge_context(reason, scopelvl, inst_depth)));
                     }
                     PolyKind::Existential => {
                         let new = ctx.instantiate_use(rhs_sub);
-                        out.push((lhs_sub, new, self.new_edge_context(reason, scopelvl)));
+                        out.push((lhs_sub, new, self.new_edge_context(reason, scopelvl, inst_depth)));
                     }
                 }
             }
         }
+        Ok(())
     }
 
     pub fn new_val(&mut self, val_type: VTypeHead, span: Span, deps: Option<PolyDeps>) -> Value {
@@ -656,14 +1020,202 @@ impl TypeCheckerCore {
         )
     }
 
+    /// Returns the tags of all variants that flow into the given use, or None if it
+    /// receives no variants at all.
+    pub fn case_tags_flowing_into(&self, u: Use) -> Option<HashSet<StringId>> {
+        if u.0 == NONE {
+            return None;
+        }
+        // Reachability is transitively closed, so every variant that reaches u is directly connected to it.
+        // In a successfully checked script, any other value heads connected to u have been unwrapped
+        // into their components by check_heads.
+        let tags: HashSet<_> = self
+            .r
+            .flows_from(u.0)
+            .filter_map(|i| match self.r.get(i) {
+                Some(TypeNode::Value((VTypeHead::VCase { case: (tag, _) }, ..))) => Some(*tag),
+                _ => None,
+            })
+            .collect();
+        if tags.is_empty() { None } else { Some(tags) }
+    }
+
+    /// Returns the type nodes that values from code which hasn't been checked yet could later
+    /// flow into via the given values, e.g. the parameters of functions that might be called later.
+    pub fn open_nodes(&self, roots: impl IntoIterator<Item = Value>) -> HashSet<TypeNodeInd> {
+        let mut open = HashSet::new();
+        self.visit_live_heads(roots.into_iter().map(|v| (v.0, true)), |i, positive| {
+            if !positive {
+                open.insert(i);
+            }
+        });
+        open
+    }
+
+    /// Calls visit on every node whose head is accessible to code that has access to the given nodes,
+    /// along with whether it is accessed as a value (true) or as a use (false).
+    fn visit_live_heads(
+        &self,
+        roots: impl IntoIterator<Item = (TypeNodeInd, bool)>,
+        mut visit: impl FnMut(TypeNodeInd, bool),
+    ) {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<_> = roots.into_iter().collect();
+        while let Some((i, positive)) = stack.pop() {
+            if i == NONE || !seen.insert((i, positive)) {
+                continue;
+            }
+
+            if positive {
+                // Code that has access to the value can access anything flowing into it
+                let heads = std::iter::once(i).chain(self.r.flows_from(i));
+                for j in heads {
+                    visit(j, true);
+                    let Some(TypeNode::Value((head, ..))) = self.r.get(j) else {
+                        continue;
+                    };
+
+                    use VTypeHead::*;
+                    match head {
+                        VUnion(vals) => stack.extend(vals.iter().map(|v| (v.0, true))),
+                        VInstantiateExist { target, .. } => stack.push((target.0, true)),
+                        VFunc { arg, ret } => stack.extend([(arg.0, false), (ret.0, true)]),
+                        VObj { fields } => {
+                            for (r, w, _) in fields.values() {
+                                stack.push((r.0, true));
+                                stack.extend(w.map(|w| (w.0, false)));
+                            }
+                        }
+                        VCase { case: (_, val) } => stack.push((val.0, true)),
+                        VPolyHead(_, val, _, bounds) => {
+                            stack.push((val.0, true));
+                            stack.extend(bounds.iter().flat_map(ParamBounds::nodes));
+                        }
+                        VDisjointIntersect(_, def) => stack.extend(def.map(|v| (v.0, true))),
+                        VTop | VAbstract { .. } | VTypeVar(..) => {}
+                    }
+                }
+            } else {
+                // Code can pass new values into the use, and hence anything it flows to
+                let heads = std::iter::once(i).chain(self.r.flows_to(i));
+                for j in heads {
+                    visit(j, false);
+                    let Some(TypeNode::Use((head, ..))) = self.r.get(j) else {
+                        continue;
+                    };
+
+                    use UTypeHead::*;
+                    match head {
+                        UIntersection(uses) => stack.extend(uses.iter().map(|u| (u.0, false))),
+                        UInstantiateUni { target, .. } => stack.push((target.0, false)),
+                        UFunc { arg, ret } => stack.extend([(arg.0, true), (ret.0, false)]),
+                        UObj { fields } => {
+                            for (r, w, _) in fields.values() {
+                                stack.push((r.0, false));
+                                stack.extend(w.map(|w| (w.0, true)));
+                            }
+                        }
+                        UCase { cases, wildcard } => {
+                            stack.extend(cases.values().map(|u| (u.0, false)));
+                            stack.extend(wildcard.map(|u| (u.0, false)));
+                        }
+                        UOverload { cases } => {
+                            for (_, flows) in cases {
+                                stack.extend(flows.iter().flat_map(|(v, u)| [(v.0, true), (u.0, false)]));
+                            }
+                        }
+                        UPolyHead(_, u, _, bounds) => {
+                            stack.push((u.0, false));
+                            stack.extend(bounds.iter().flat_map(ParamBounds::nodes));
+                        }
+                        UDisjointUnion(_, def) => stack.extend(def.map(|u| (u.0, false))),
+                        UBot | UAbstract { .. } | UTypeVar(..) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn num_type_nodes(&self) -> usize {
+        self.r.len()
+    }
+
+    /// Inference variables created for explicit _ annotations since the given node count.
+    pub fn explicit_holes(&self, start: usize) -> Vec<(Span, TypeNodeInd)> {
+        (start..self.r.len())
+            .map(TypeNodeInd)
+            .filter_map(|i| match self.r.get(i)? {
+                TypeNode::Var(InferenceVarData {
+                    src: HoleSrc::Explicit(span),
+                    ..
+                }) => Some((*span, i)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Remove every type node that can no longer affect type checking of future code, given that
+    /// only the given values are still accessible. Returns the new index of each old node that was kept.
+    pub fn compact(&mut self, roots: impl IntoIterator<Item = Value>) -> Vec<Option<TypeNodeInd>> {
+        // Nodes whose heads may take part in future flows. Bounds of abstract types are always kept,
+        // since later scripts can refer to the types by name.
+        let ctor_bounds = self.type_ctors.iter().flat_map(|ctor| {
+            let upper = ctor.upper.map(|v| (v.0, true));
+            upper.into_iter().chain(ctor.lower.map(|u| (u.0, false)))
+        });
+        let roots: Vec<_> = roots.into_iter().map(|v| (v.0, true)).chain(ctor_bounds).collect();
+        let mut keep = HashSet::new();
+        self.visit_live_heads(roots, |i, _| {
+            keep.insert(i);
+        });
+
+        // Kept nodes must not refer to removed nodes, either through their heads or through
+        // the reasons recorded on the edges between them, which are used to explain type errors.
+        let mut stack: Vec<_> = keep.iter().copied().collect();
+        let mut components = Vec::new();
+        while let Some(i) = stack.pop() {
+            self.r.get_mut(i).unwrap().visit_inds(&mut |_, j| components.push(*j));
+
+            let edges = self.r.flows_from(i).map(|j| (j, i)).chain(self.r.flows_to(i).map(|j| (i, j)));
+            for (lhs, rhs) in edges {
+                if !keep.contains(&lhs) || !keep.contains(&rhs) {
+                    continue;
+                }
+                match self.r.get_edge(lhs, rhs).unwrap().reason {
+                    FlowReason::Root(_) => {}
+                    FlowReason::Transitivity(h) => components.push(h),
+                    FlowReason::Check(v, u) => components.extend([v.0, u.0]),
+                }
+            }
+
+            for j in components.drain(..) {
+                if j != NONE && keep.insert(j) {
+                    stack.push(j);
+                }
+            }
+        }
+
+        let mapping = self.r.compact(&keep);
+        for ctor in self.type_ctors.iter_mut() {
+            ctor.upper = ctor.upper.map(|v| Value(mapping[v.0.0].unwrap()));
+            ctor.lower = ctor.lower.map(|u| Use(mapping[u.0.0].unwrap()));
+        }
+        mapping
+    }
+
     ////////////////////////////////////////////////////////////////////////////////
     pub fn save(&mut self) {
         self.r.save();
+        self.saved_type_ctors.push(self.type_ctors.len());
     }
     pub fn revert(&mut self) {
         self.r.revert();
+        // Type constructors created since the save may have bounds referring to removed nodes
+        let len = self.saved_type_ctors.pop().unwrap();
+        self.type_ctors.truncate(len);
     }
     pub fn make_permanent(&mut self) {
         self.r.make_permanent();
+        self.saved_type_ctors.pop();
     }
 }
@@ -0,0 +1,286 @@
+use crate::core::*;
+use crate::reachability::TypeNodeInd;
+use crate::spans::Span;
+use crate::spans::SpanManager;
+use crate::type_errors::HoleSrc;
+use crate::type_errors::describe_use_head;
+use crate::type_errors::describe_value_head;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+impl GraphFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" => Some(GraphFormat::Dot),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+struct GraphNode {
+    id: usize,
+    // Whether the node was created by an earlier script
+    earlier: bool,
+    kind: &'static str,
+    desc: String,
+    span: Option<Span>,
+    scopelvl: Option<u32>,
+    // Labelled references to the other nodes in the head
+    children: Vec<(String, usize)>,
+}
+
+struct GraphEdge {
+    lhs: usize,
+    rhs: usize,
+    scopelvl: u32,
+    reason: String,
+    bound_pairs: Vec<(Span, Span)>,
+}
+
+/// A snapshot of the part of the type graph created by a single script, for debugging.
+pub struct ConstraintGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+impl ConstraintGraph {
+    pub fn print(&self, sm: &SpanManager, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(sm),
+            GraphFormat::Json => self.to_json(sm),
+        }
+    }
+
+    fn to_dot(&self, sm: &SpanManager) -> String {
+        let mut out = String::from("digraph constraints {\n    node [shape=box];\n");
+        for node in self.nodes.iter() {
+            let mut label = format!("#{} {}: {}", node.id, node.kind, node.desc);
+            if let Some(lvl) = node.scopelvl {
+                label += &format!("\nscope level {}", lvl);
+            }
+            if let Some(span) = node.span {
+                label += "\n";
+                label += &sm.describe(span);
+            }
+            let style = if node.earlier { ", style=dashed" } else { "" };
+            out += &format!("    n{} [label={}{}];\n", node.id, dot_str(&label), style);
+            for (name, child) in node.children.iter() {
+                out += &format!(
+                    "    n{} -> n{} [label={}, style=dotted, arrowhead=odot];\n",
+                    node.id,
+                    child,
+                    dot_str(name)
+                );
+            }
+        }
+        for edge in self.edges.iter() {
+            let mut label = format!("lvl {}\n{}", print_scopelvl(edge.scopelvl), edge.reason);
+            for &(l, r) in edge.bound_pairs.iter() {
+                label += &format!("\nbound {} ~ {}", sm.describe(l), sm.describe(r));
+            }
+            out += &format!("    n{} -> n{} [label={}];\n", edge.lhs, edge.rhs, dot_str(&label));
+        }
+        out += "}\n";
+        out
+    }
+
+    fn to_json(&self, sm: &SpanManager) -> String {
+        let span = |span: Option<Span>| span.map(|s| json_str(&sm.describe(s))).unwrap_or_else(|| "null".to_owned());
+
+        let nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let children: Vec<_> = node
+                    .children
+                    .iter()
+                    .map(|(name, child)| format!("{{\"label\": {}, \"id\": {}}}", json_str(name), child))
+                    .collect();
+                format!(
+                    "{{\"id\": {}, \"earlier\": {}, \"kind\": {}, \"desc\": {}, \"span\": {}, \"scope_level\": {}, \"children\": [{}]}}",
+                    node.id,
+                    node.earlier,
+                    json_str(node.kind),
+                    json_str(&node.desc),
+                    span(node.span),
+                    node.scopelvl.map(json_scopelvl).unwrap_or_else(|| "null".to_owned()),
+                    children.join(", ")
+                )
+            })
+            .collect();
+
+        let edges: Vec<_> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let pairs: Vec<_> = edge
+                    .bound_pairs
+                    .iter()
+                    .map(|&(l, r)| format!("[{}, {}]", span(Some(l)), span(Some(r))))
+                    .collect();
+                format!(
+                    "{{\"from\": {}, \"to\": {}, \"scope_level\": {}, \"reason\": {}, \"bound_pairs\": [{}]}}",
+                    edge.lhs,
+                    edge.rhs,
+                    json_scopelvl(edge.scopelvl),
+                    json_str(&edge.reason),
+                    pairs.join(", ")
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"nodes\": [\n    {}\n  ],\n  \"edges\": [\n    {}\n  ]\n}}\n",
+            nodes.join(",\n    "),
+            edges.join(",\n    ")
+        )
+    }
+}
+
+fn print_scopelvl(lvl: u32) -> String {
+    if lvl == u32::MAX { "none".to_owned() } else { lvl.to_string() }
+}
+
+fn json_scopelvl(lvl: u32) -> String {
+    if lvl == u32::MAX { "null".to_owned() } else { lvl.to_string() }
+}
+
+fn dot_str(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn hole_desc(strings: &lasso::Rodeo, src: HoleSrc) -> String {
+    use HoleSrc::*;
+    match src {
+        Explicit(_) => "explicit _ annotation".to_owned(),
+        OptAscribe(_) => "missing optional annotation".to_owned(),
+        Instantiation(_, name) => format!("instantiation of type parameter {}", strings.resolve(&name)),
+        CheckedExpr(_) => "checked expression".to_owned(),
+        BareVarPattern(_) => "variable pattern".to_owned(),
+    }
+}
+
+fn child_label(strings: &lasso::Rodeo, role: ChildRole) -> String {
+    use ChildRole::*;
+    let half = |is_use: bool| if is_use { "use" } else { "value" };
+    match role {
+        Member => "member".to_owned(),
+        Target => "target".to_owned(),
+        Param(name, is_use) => format!("{} ({})", strings.resolve(&name), half(is_use)),
+        Arg => "arg".to_owned(),
+        Ret => "ret".to_owned(),
+        Field(name) => strings.resolve(&name).to_owned(),
+        FieldWrite(name) => format!("{} (write)", strings.resolve(&name)),
+        Tag(tag) => format!("`{}", strings.resolve(&tag)),
+        Wildcard => "_".to_owned(),
+        OverloadCase(i, is_use) => format!("case {} ({})", i, half(is_use)),
+        Body => "body".to_owned(),
+        Bound(name, upper, is_use) => {
+            let op = if upper { "<:" } else { ":>" };
+            format!("{} {} ({})", strings.resolve(&name), op, half(is_use))
+        }
+        Default => "default".to_owned(),
+    }
+}
+
+impl TypeCheckerCore {
+    fn graph_node(&self, strings: &lasso::Rodeo, i: TypeNodeInd, earlier: bool) -> GraphNode {
+        let node = self.r.get(i).unwrap();
+        let (kind, desc, span, scopelvl) = match node {
+            TypeNode::Var(data) => {
+                let desc = hole_desc(strings, data.src);
+                ("hole", desc, Some(data.src.span()), Some(data.scopelvl.0))
+            }
+            TypeNode::Value((head, span, _)) => {
+                let desc = describe_value_head(strings, &self.type_ctors, head);
+                ("value", desc, Some(*span), None)
+            }
+            TypeNode::Use((head, span, _)) => {
+                let desc = describe_use_head(strings, &self.type_ctors, head);
+                ("use", desc, Some(*span), None)
+            }
+            TypeNode::Placeholder => ("placeholder", String::new(), None, None),
+        };
+
+        GraphNode {
+            id: i.0,
+            earlier,
+            kind,
+            desc,
+            span,
+            scopelvl,
+            // Bot and top are not real nodes
+            children: node
+                .children()
+                .into_iter()
+                .filter(|(_, c)| *c != NONE)
+                .map(|(role, c)| (child_label(strings, role), c.0))
+                .collect(),
+        }
+    }
+
+    /// Returns the nodes created since the given node count, along with every flow edge involving
+    /// them. Nodes created earlier are only included when a new node refers to them.
+    pub fn constraint_graph(&self, strings: &lasso::Rodeo, start: usize) -> ConstraintGraph {
+        let mut edges = Vec::new();
+        let mut earlier = Vec::new();
+        for i in (start..self.r.len()).map(TypeNodeInd) {
+            // Include edges from earlier nodes to new nodes, and every edge from a new node
+            let preds = self.r.flows_from(i).filter(|j| j.0 < start);
+            let pairs = preds.map(|j| (j, i)).chain(self.r.flows_to(i).map(|j| (i, j)));
+            for (lhs, rhs) in pairs {
+                earlier.extend([lhs, rhs].into_iter().filter(|j| j.0 < start));
+
+                let edge = self.r.get_edge(lhs, rhs).unwrap();
+                let reason = match edge.reason {
+                    FlowReason::Root(_) => "root".to_owned(),
+                    FlowReason::Transitivity(h) => format!("via #{}", h.0),
+                    FlowReason::Check(v, u) => format!("check #{} -> #{}", v.0.0, u.0.0),
+                };
+                edges.push(GraphEdge {
+                    lhs: lhs.0,
+                    rhs: rhs.0,
+                    scopelvl: edge.scopelvl.0,
+                    reason,
+                    bound_pairs: edge
+                        .bound_pairs
+                        .pairs()
+                        .into_iter()
+                        .map(|(l, r)| (l.span(), r.span()))
+                        .collect(),
+                });
+            }
+        }
+
+        let new_nodes: Vec<_> = (start..self.r.len())
+            .map(|i| self.graph_node(strings, TypeNodeInd(i), false))
+            .collect();
+        for node in new_nodes.iter() {
+            earlier.extend(node.children.iter().map(|&(_, c)| TypeNodeInd(c)).filter(|j| j.0 < start));
+        }
+        earlier.sort_unstable();
+        earlier.dedup();
+
+        let mut nodes: Vec<_> = earlier.into_iter().map(|i| self.graph_node(strings, i, true)).collect();
+        nodes.extend(new_nodes);
+        ConstraintGraph { nodes, edges }
+    }
+}
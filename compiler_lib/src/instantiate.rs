@@ -89,14 +89,15 @@ impl InstantionContext<'_> {
                 case: (tag, self.instantiate_val(ty)),
             },
 
-            VPolyHead(poly, ty, poison) => {
+            // Bounds can't refer to type parameters, so they never need to be instantiated
+            VPolyHead(poly, ty, poison, bounds) => {
                 let poison = poison || matches!(self.subs, Substitutions::Type(..));
-                VPolyHead(poly, self.instantiate_val(ty), poison)
+                VPolyHead(poly, self.instantiate_val(ty), poison, bounds)
             }
 
             VDisjointIntersect(vars, default) => {
                 assert!(!vars.iter().any(|spec| spec.loc == self.root));
-                VDisjointIntersect(vars.clone(), default.clone().map(|t| self.instantiate_val(t)))
+                VDisjointIntersect(vars.clone(), default.map(|t| self.instantiate_val(t)))
             }
         };
 
@@ -140,7 +141,7 @@ impl InstantionContext<'_> {
         self.umap.insert(old, ph);
 
         let head = match node.0 {
-            UInstantiateUni { .. } | UBot | UAbstract { .. } | UTypeVar(..) => unreachable!(),
+            UInstantiateUni { .. } | UBot | UAbstract { .. } | UOverload { .. } | UTypeVar(..) => unreachable!(),
 
             UIntersection(uses) => UIntersection(uses.into_iter().map(|u| self.instantiate_use(u)).collect()),
 
@@ -166,9 +167,9 @@ impl InstantionContext<'_> {
                 }
             }
 
-            UPolyHead(poly, ty, poison) => {
+            UPolyHead(poly, ty, poison, bounds) => {
                 let poison = poison || matches!(self.subs, Substitutions::Type(..));
-                UPolyHead(poly, self.instantiate_use(ty), poison)
+                UPolyHead(poly, self.instantiate_use(ty), poison, bounds)
             }
 
             UDisjointUnion(vars, default) => {
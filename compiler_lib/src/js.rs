@@ -17,6 +17,9 @@ pub enum Op {
 
     Eq,
     Neq,
+
+    BitAnd,
+    BitOr,
 }
 
 /////////////////////////////////////////////////////////////////////////////////////////////
@@ -27,7 +30,10 @@ pub fn binop(lhs: Expr, rhs: Expr, op: Op) -> Expr {
     Expr(Expr2::BinOp(lhs.0.into(), rhs.0.into(), op))
 }
 pub fn call(lhs: Expr, rhs: Expr) -> Expr {
-    Expr(Expr2::Call(lhs.0.into(), rhs.0.into()))
+    Expr(Expr2::Call(lhs.0.into(), vec![rhs.0]))
+}
+pub fn call_multi(lhs: Expr, args: Vec<Expr>) -> Expr {
+    Expr(Expr2::Call(lhs.0.into(), args.into_iter().map(|e| e.0).collect()))
 }
 pub fn unary_minus(rhs: Expr) -> Expr {
     Expr(Expr2::Minus(rhs.0.into()))
@@ -118,6 +124,8 @@ enum Token {
     PAREN,
 }
 
+// Named after the corresponding productions in the JS grammar
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum Precedence {
     PRIMARY = 0,
@@ -131,6 +139,8 @@ enum Precedence {
     SHIFT,
     RELATIONAL,
     EQUALITY,
+    BAND,
+    BOR,
     LOR,
     CONDITIONAL,
     ASSIGN,
@@ -153,7 +163,7 @@ enum Expr2 {
     Field(Box<Expr2>, String),
     ScopeField(String, String),
 
-    Call(Box<Expr2>, Box<Expr2>),
+    Call(Box<Expr2>, Vec<Expr2>),
 
     Minus(Box<Expr2>),
     Void,
@@ -192,6 +202,8 @@ impl Expr2 {
                 Add | Sub => ADDITIVE,
                 Lt | Lte | Gt | Gte => RELATIONAL,
                 Eq | Neq => EQUALITY,
+                BitAnd => BAND,
+                BitOr => BOR,
             },
             Ternary(..) => CONDITIONAL,
             Assignment(..) => ASSIGN,
@@ -262,10 +274,13 @@ impl Expr2 {
                 *out += ".";
                 *out += s2;
             }
-            Self::Call(lhs, rhs) => {
+            Self::Call(lhs, args) => {
                 lhs.write(out);
                 *out += "(";
-                rhs.write(out);
+                let mut cw = CommaListWrite::new(out);
+                for ex in args {
+                    cw.write(|out| ex.write(out));
+                }
                 *out += ")";
             }
             Self::Minus(e) => {
@@ -291,6 +306,9 @@ impl Expr2 {
 
                     Eq => "===",
                     Neq => "!==",
+
+                    BitAnd => "&",
+                    BitOr => "|",
                 };
 
                 lhs.write(out);
@@ -373,11 +391,13 @@ impl Expr2 {
                 lhs.ensure(MEMBER);
             }
             Self::ScopeField(..) => {}
-            Self::Call(lhs, rhs) => {
+            Self::Call(lhs, args) => {
                 lhs.add_parens();
                 lhs.ensure(MEMBER);
-                rhs.add_parens();
-                rhs.ensure(ASSIGN);
+                for ex in args {
+                    ex.add_parens();
+                    ex.ensure(ASSIGN);
+                }
             }
             Self::Minus(e) => {
                 e.add_parens();
@@ -391,6 +411,8 @@ impl Expr2 {
                     Add | Sub => (ADDITIVE, MULTIPLICATIVE),
                     Lt | Lte | Gt | Gte => (RELATIONAL, SHIFT),
                     Eq | Neq => (EQUALITY, RELATIONAL),
+                    BitAnd => (BAND, EQUALITY),
+                    BitOr => (BOR, BAND),
                 };
 
                 lhs.add_parens();
@@ -529,8 +551,10 @@ impl DeadCodeRemover {
             ScopeField(s1, s2) => {
                 self.add_var(s1, s2.clone());
             }
-            Call(lhs, rhs) => {
-                self.process_used_expr(rhs);
+            Call(lhs, args) => {
+                for ex in args.iter_mut().rev() {
+                    self.process_used_expr(ex);
+                }
                 self.process_used_expr(lhs);
             }
             Minus(e) => {
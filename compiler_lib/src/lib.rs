@@ -7,27 +7,41 @@ mod bound_pairs_set;
 mod codegen;
 mod core;
 mod grammar;
+mod graph_dump;
 mod instantiate;
 mod js;
 mod parse_types;
 mod reachability;
+mod reconstruct;
 mod spans;
 mod type_errors;
 mod typeck;
 mod unwindmap;
+mod warnings;
 
 use lasso::Rodeo;
 
+use std::collections::HashSet;
 use std::mem;
 
 use lalrpop_util::ParseError;
 
+pub use self::core::Limits;
+pub use self::graph_dump::GraphFormat;
+pub use self::spans::TextEdit;
+
 use self::codegen::ModuleBuilder;
+use self::grammar::InterfaceParser;
+use self::grammar::STypeParser;
 use self::grammar::ScriptParser;
 use self::spans::SpanMaker;
 use self::spans::SpanManager;
 use self::spans::SpannedError;
+use self::typeck::ExprType;
 use self::typeck::TypeckState;
+use self::warnings::Warning;
+use self::warnings::WarningChecker;
+use self::warnings::WarningKind;
 
 fn convert_parse_error<T: std::fmt::Display>(
     mut sm: SpanMaker,
@@ -59,11 +73,43 @@ fn convert_parse_error<T: std::fmt::Display>(
     }
 }
 
+/// Functions provided by the JS runtime, as (name, type signature, JS function name)
+const BUILTINS: &[(&str, &str, &str)] = &[
+    // Explicit conversions between int and the fixed width integer types. Conversions to
+    // fixed width types wrap around if the value is out of range.
+    ("i32_of_int", "int -> i32", "i32_of_int"),
+    ("int_of_i32", "i32 -> int", "int_of_i32"),
+    ("i64_of_int", "int -> i64", "i64_of_int"),
+    ("int_of_i64", "i64 -> int", "int_of_i64"),
+    ("u8_of_int", "int -> u8", "u8_of_int"),
+    ("int_of_u8", "u8 -> int", "int_of_u8"),
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CompilationResult {
     Success(String), // Contains compiled JS code
     Error(String),   // Contains error message
 }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubtypeResult {
+    Subtype,
+    NotSubtype(String), // Contains explanation of why not
+    Error(String),      // Contains error message if either type is invalid
+}
+
+/// The type of a single expression in a successfully compiled source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedExpr {
+    /// Start and end byte offsets of the expression within the source
+    pub start: usize,
+    pub end: usize,
+    /// Whether the type was inferred from the expression itself, rather than being
+    /// the type that the expression was checked against
+    pub inferred: bool,
+    /// The type, printed in PolySubML type syntax
+    pub ty: String,
+}
+
 impl std::fmt::Display for CompilationResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -80,23 +126,74 @@ pub struct State {
 
     checker: TypeckState,
     compiler: ModuleBuilder,
+
+    // Top level variables defined by the last successfully compiled script
+    last_defined: Vec<ast::StringId>,
+    // Source index of the script most recently passed to process. Types and interfaces
+    // passed to other methods are added as sources too, so this isn't just the last source.
+    last_script_source: Option<usize>,
+
+    enabled_warnings: HashSet<WarningKind>,
+    // Warnings for the last successfully compiled script
+    warnings: Vec<Warning>,
+    // Fixes suggested by the errors in the last script, if it failed to compile
+    fixes: Vec<Vec<TextEdit>>,
 }
 impl State {
     pub fn new() -> Self {
         let mut strings = Rodeo::new();
         let checker = TypeckState::new(&mut strings);
 
-        State {
+        let mut new = State {
             parser: ScriptParser::new(),
             spans: SpanManager::default(),
             strings,
 
             checker,
             compiler: ModuleBuilder::new(),
+
+            last_defined: Vec::new(),
+            last_script_source: None,
+
+            enabled_warnings: WarningKind::ALL.into_iter().collect(),
+            warnings: Vec::new(),
+            fixes: Vec::new(),
+        };
+        new.add_builtins();
+        new
+    }
+
+    fn parse_type(&mut self, source: &str) -> Result<ast::STypeExpr, SpannedError> {
+        let span_maker = self.spans.add_source(source.to_owned());
+        let mut ctx = ast::ParserContext {
+            span_maker,
+            strings: &mut self.strings,
+        };
+
+        STypeParser::new()
+            .parse(&mut ctx, source)
+            .map_err(|e| convert_parse_error(ctx.span_maker, e))
+    }
+
+    fn add_builtins(&mut self) {
+        for &(name, sig, js_name) in BUILTINS {
+            let name = self.strings.get_or_intern_static(name);
+            let ty = self.parse_type(sig).expect("Invalid builtin type signature");
+            self.checker
+                .add_builtin_var(name, &ty)
+                .expect("Invalid builtin type signature");
+            self.compiler.add_builtin_var(name, js_name);
         }
+
+        // compare is overloaded over the primitive types, which can't be written as a type signature
+        let name = self.strings.get_or_intern_static("compare");
+        let span = self.spans.add_source("compare".to_owned()).span(0, 7);
+        self.checker.add_builtin_compare(name, span);
+        self.compiler.add_builtin_var(name, "compare");
     }
 
-    fn process_sub(&mut self, source: &str) -> Result<String, SpannedError> {
+    fn process_sub(&mut self, source: &str) -> Result<String, Vec<SpannedError>> {
+        self.last_script_source = Some(self.spans.num_sources());
         let span_maker = self.spans.add_source(source.to_owned());
         let mut ctx = ast::ParserContext {
             span_maker,
@@ -106,24 +203,267 @@ impl State {
         let ast = self
             .parser
             .parse(&mut ctx, source)
-            .map_err(|e| convert_parse_error(ctx.span_maker, e))?;
-        let _t = self.checker.check_script(&mut self.strings, &ast)?;
+            .map_err(|e| vec![convert_parse_error(ctx.span_maker, e)])?;
+        self.last_defined = self.checker.check_script(&mut self.strings, &ast)?;
+        self.warnings = WarningChecker::new(&self.strings, &self.enabled_warnings).check_script(&ast);
+        let mut checker_warnings = self.checker.match_warnings(&self.strings);
+        checker_warnings.extend(self.checker.generalize_warnings());
+        self.warnings.extend(
+            checker_warnings
+                .into_iter()
+                .filter(|w| self.enabled_warnings.contains(&w.kind)),
+        );
+        self.warnings.sort_by_key(|w| self.spans.get(w.span).1);
 
-        let mut ctx = codegen::Context(&mut self.compiler, &self.strings);
+        let fixed_width_prints = self.checker.fixed_width_prints();
+        let mut ctx = codegen::Context(&mut self.compiler, &self.strings, &fixed_width_prints);
         let js_ast = codegen::compile_script(&mut ctx, &ast);
         Ok(js_ast.to_source())
     }
 
     pub fn process(&mut self, source: &str) -> CompilationResult {
+        self.last_defined.clear();
+        self.warnings.clear();
+        self.checker.clear_last_graph();
+        self.fixes.clear();
         let res = self.process_sub(source);
         match res {
             Ok(s) => CompilationResult::Success(s),
-            Err(e) => CompilationResult::Error(e.print(&self.spans)),
+            Err(errors) => {
+                self.fixes = errors.iter().flat_map(|e| e.suggestions(&self.spans)).collect();
+                let msgs: Vec<_> = errors.iter().map(|e| e.print(&self.spans)).collect();
+                CompilationResult::Error(msgs.join("\n"))
+            }
+        }
+    }
+
+    /// Inferred type of a top level variable, printed in PolySubML type syntax.
+    pub fn binding_type(&mut self, name: &str) -> Option<String> {
+        let name = self.strings.get(name)?;
+        self.checker.binding_type(&self.strings, name)
+    }
+
+    /// Check whether every value of type a is also a value of type b, where a and b are type
+    /// signatures written in PolySubML syntax. Type names defined by previous scripts are in scope.
+    pub fn is_subtype(&mut self, a: &str, b: &str) -> SubtypeResult {
+        let (a, b) = match (self.parse_type(a), self.parse_type(b)) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => return SubtypeResult::Error(e.print(&self.spans)),
+        };
+        match self.checker.is_subtype(&mut self.strings, &a, &b) {
+            Ok(Ok(())) => SubtypeResult::Subtype,
+            Ok(Err(e)) => SubtypeResult::NotSubtype(e.print(&self.spans)),
+            Err(e) => SubtypeResult::Error(e.print(&self.spans)),
+        }
+    }
+
+    /// An interface listing the top level variables defined by the last successfully processed script
+    /// and their inferred types, in the form accepted by check_interface.
+    /// Inference variables that are not constrained yet are written as `any` or `never` rather than `_`,
+    /// so that the interface still rejects incompatible changes.
+    pub fn interface(&self) -> String {
+        self.last_defined
+            .iter()
+            .filter_map(|&name| {
+                let ty = self.checker.interface_type(&self.strings, name)?;
+                Some(format!("val {}: {};\n", self.strings.resolve(&name), ty))
+            })
+            .collect()
+    }
+
+    /// Check that the top level variables defined so far match an interface consisting of
+    /// `val name: type;` declarations, i.e. each declared variable exists, and its inferred
+    /// type is a subtype of the declared type. Returns the error messages on failure.
+    pub fn check_interface(&mut self, source: &str) -> Result<(), String> {
+        let span_maker = self.spans.add_source(source.to_owned());
+        let mut ctx = ast::ParserContext {
+            span_maker,
+            strings: &mut self.strings,
+        };
+
+        let res = InterfaceParser::new()
+            .parse(&mut ctx, source)
+            .map_err(|e| vec![convert_parse_error(ctx.span_maker, e)])
+            .and_then(|decls| self.checker.check_interface(&mut self.strings, &decls));
+        res.map_err(|errors| {
+            let msgs: Vec<_> = errors.iter().map(|e| e.print(&self.spans)).collect();
+            msgs.join("\n")
+        })
+    }
+
+    /// Names and inferred types of the top level variables defined by the last successfully processed script.
+    pub fn defined_bindings(&self) -> Vec<(String, String)> {
+        self.last_defined
+            .iter()
+            .filter_map(|&name| {
+                let ty = self.checker.binding_type(&self.strings, name)?;
+                Some((self.strings.resolve(&name).to_owned(), ty))
+            })
+            .collect()
+    }
+
+    /// Names of the warnings that can be enabled or disabled. All warnings are enabled by default.
+    pub fn warning_names() -> impl Iterator<Item = &'static str> {
+        WarningKind::ALL.into_iter().map(WarningKind::name)
+    }
+
+    /// Enable or disable the warning with the given name. Returns false if there is no such warning.
+    pub fn set_warning_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let Some(kind) = WarningKind::from_name(name) else {
+            return false;
+        };
+        if enabled {
+            self.enabled_warnings.insert(kind);
+        } else {
+            self.enabled_warnings.remove(&kind);
         }
+        true
+    }
+
+    /// Names and messages of the warnings for the last successfully processed script.
+    pub fn warnings(&self) -> Vec<(&'static str, String)> {
+        self.warnings.iter().map(|w| (w.kind.name(), w.print(&self.spans))).collect()
+    }
+
+    /// Edits suggested by the errors for the last processed script, such as adding a type annotation.
+    /// Each fix consists of one or more edits which should be applied together with TextEdit::apply_all.
+    pub fn suggested_fixes(&self) -> &[Vec<TextEdit>] {
+        &self.fixes
+    }
+
+    /// Index of the source added by the most recent call to process, for use with type_at.
+    pub fn last_source_index(&self) -> Option<usize> {
+        self.last_script_source
+    }
+
+    /// Type of the innermost expression enclosing the given byte offset in the given source,
+    /// printed in PolySubML type syntax. Only the most recently processed source is available,
+    /// and only if it compiled successfully.
+    pub fn type_at(&self, source_index: usize, offset: usize) -> Option<String> {
+        let (_, ty) = self
+            .checker
+            .expr_types()
+            .iter()
+            .filter_map(|&(span, ty)| {
+                let (source, l, r) = self.spans.get(span);
+                if source != source_index || offset < l || offset > r {
+                    return None;
+                }
+                // Prefer the innermost expression, and inferred types over expected types
+                Some(((r - l, matches!(ty, ExprType::Use(..))), ty))
+            })
+            .min_by_key(|&(key, _)| key)?;
+        Some(self.checker.print_expr_type(&self.strings, ty))
+    }
+
+    /// Types of every expression in the given source, ordered by start offset with enclosing expressions
+    /// first. An expression may have both an inferred and an expected type. Only the most recently
+    /// processed source is available, and only if it compiled successfully.
+    pub fn expr_types(&self, source_index: usize) -> Vec<TypedExpr> {
+        let mut out: Vec<_> = self
+            .checker
+            .expr_types()
+            .iter()
+            .filter_map(|&(span, ty)| {
+                let (source, start, end) = self.spans.get(span);
+                (source == source_index).then(|| TypedExpr {
+                    start,
+                    end,
+                    inferred: matches!(ty, ExprType::Value(..)),
+                    ty: self.checker.print_expr_type(&self.strings, ty),
+                })
+            })
+            .collect();
+        out.sort_by_key(|e| (e.start, std::cmp::Reverse(e.end), !e.inferred));
+        out
+    }
+
+    /// Limit the work done by the type checker. When a limit is hit, the script fails
+    /// to compile with an error pointing at the expression being checked.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.checker.set_limits(limits);
+    }
+
+    /// When enabled, type errors also list every step by which the offending value
+    /// flowed from its origin to the place where it was rejected.
+    pub fn set_verbose_errors(&mut self, enabled: bool) {
+        self.checker.set_verbose_errors(enabled);
+    }
+
+    /// Save the type constraint graph created by each processed script, so that it
+    /// can be retrieved with constraint_graph. This is off by default since it is slow.
+    pub fn set_record_graph(&mut self, enabled: bool) {
+        self.checker.set_record_graph(enabled);
+    }
+
+    /// The nodes and flow edges of the type graph created by the last processed script, including
+    /// scripts which failed to compile. Requires set_record_graph to be enabled beforehand.
+    pub fn constraint_graph(&self, format: GraphFormat) -> Option<String> {
+        Some(self.checker.last_graph()?.print(&self.spans, format))
+    }
+
+    /// Save the types inferred for explicit _ annotations in each processed script,
+    /// so that they can be retrieved with typed_holes.
+    pub fn set_typed_holes(&mut self, enabled: bool) {
+        self.checker.set_record_holes(enabled);
+    }
+
+    /// Messages giving the inferred type of each explicit _ annotation in the last successfully
+    /// processed script, in source order. Requires set_typed_holes to be enabled beforehand.
+    pub fn typed_holes(&self) -> Vec<String> {
+        let mut holes = self.checker.typed_holes(&self.strings);
+        holes.sort_by_key(|&(span, ..)| self.spans.get(span).1);
+        holes
+            .into_iter()
+            .map(|(span, val, u)| {
+                let msg = format!("Found hole _\n  inferred type: {}\n  used as: {}", val, u);
+                SpannedError::new1(msg, span).print(&self.spans)
+            })
+            .collect()
+    }
+
+    /// Give unannotated function definitions like `let f = fun x -> x` a polymorphic type when possible,
+    /// as if they had been written with explicit type parameters. Functions which can't be generalized
+    /// keep their usual monomorphic type, with a not-generalized warning explaining why.
+    pub fn set_generalize(&mut self, enabled: bool) {
+        self.checker.set_generalize(enabled);
+    }
+
+    /// Type +, -, * and the int comparison operators so that they also work on floats.
+    pub fn set_overloaded_arith(&mut self, enabled: bool) {
+        self.checker.set_overloaded_arith(enabled);
+    }
+
+    /// Number of nodes currently in the type graph. This grows with every processed script.
+    pub fn num_type_nodes(&self) -> usize {
+        self.checker.num_type_nodes()
+    }
+
+    /// Shrink the type graph by removing type information that is no longer reachable from
+    /// any top level variable. Types of expressions from earlier sources may no longer be
+    /// available from type_at afterwards. Returns the number of type nodes removed.
+    pub fn compact(&mut self) -> usize {
+        self.checker.compact()
     }
 
     pub fn reset(&mut self) {
+        let overloaded_arith = self.checker.overloaded_arith();
+        let limits = self.checker.limits();
+        let verbose_errors = self.checker.verbose_errors();
+        let record_graph = self.checker.record_graph();
+        let record_holes = self.checker.record_holes();
+        let generalize = self.checker.generalize();
         mem::swap(&mut self.checker, &mut TypeckState::new(&mut self.strings));
+        self.checker.set_overloaded_arith(overloaded_arith);
+        self.checker.set_limits(limits);
+        self.checker.set_verbose_errors(verbose_errors);
+        self.checker.set_record_graph(record_graph);
+        self.checker.set_record_holes(record_holes);
+        self.checker.set_generalize(generalize);
+        self.last_defined.clear();
+        self.warnings.clear();
+        self.fixes.clear();
         mem::swap(&mut self.compiler, &mut ModuleBuilder::new());
+        self.add_builtins();
     }
 }
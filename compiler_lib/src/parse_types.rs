@@ -25,13 +25,20 @@ type Result<T> = std::result::Result<T, SyntaxError>;
 // Represent distinct declarations of polymorphic/existential types in the source code
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SourceLoc(Span);
+impl SourceLoc {
+    pub fn span(self) -> Span {
+        self.0
+    }
+}
 
 enum ParsedTypeHead {
+    // Alias pattern - the first type is the type bound to the alias name
+    Alias(RcParsedType, RcParsedType),
     Case(HashMap<StringId, (Span, RcParsedType)>),
     Func(RcParsedType, RcParsedType),
     Record(HashMap<StringId, (Span, RcParsedType, Option<RcParsedType>)>),
 
-    PolyHead(Rc<PolyHeadData>, RcParsedType),
+    PolyHead(Rc<PolyHeadData>, ParsedBounds, RcParsedType),
     PolyVar(VarSpec),
     RecHead(SourceLoc, RcParsedType),
     RecVar(SourceLoc),
@@ -45,6 +52,8 @@ enum ParsedTypeHead {
 }
 type ParsedType = (PolyAndRecDeps, Span, ParsedTypeHead);
 type RcParsedType = Rc<ParsedType>;
+// Upper and lower bounds of the bounded parameters of a polymorphic type
+type ParsedBounds = Rc<[(StringId, Option<RcParsedType>, Option<RcParsedType>)]>;
 
 #[derive(Debug, Default, Clone)]
 pub struct PolyDeps(HashSet<SourceLoc>);
@@ -132,6 +141,12 @@ impl<'a> TreeMaterializer<'a> {
 
         let deps = self.eval(&ty.0);
         let (vhead, uhead) = match &ty.2 {
+            Alias(alias, sub) => {
+                let alias = self.materialize_tree(alias);
+                let sub = self.materialize_tree(sub);
+                // Values matching the pattern must flow to both the alias binding and the sub pattern
+                (VUnion(vec![alias.0]), UIntersection(vec![alias.1, sub.1]))
+            }
             &Case(ref cases) => {
                 let mut utype_case_arms = HashMap::new();
                 let mut vtype_case_arms = Vec::new();
@@ -183,9 +198,13 @@ impl<'a> TreeMaterializer<'a> {
 
                 (VObj { fields: vtype_fields }, UObj { fields: utype_fields })
             }
-            &PolyHead(ref data, ref sub) => {
+            PolyHead(data, bounds, sub) => {
+                let bounds = self.materialize_bounds(bounds);
                 let sub = self.materialize_tree(sub);
-                (VPolyHead(data.clone(), sub.0, false), UPolyHead(data.clone(), sub.1, false))
+                (
+                    VPolyHead(data.clone(), sub.0, false, bounds.clone()),
+                    UPolyHead(data.clone(), sub.1, false, bounds),
+                )
             }
             &PolyVar(spec) => (VTypeVar(spec), UTypeVar(spec)),
             &RecHead(loc, ref sub) => {
@@ -239,7 +258,7 @@ impl<'a> TreeMaterializer<'a> {
     fn materialize_tree_sub(&mut self, ty: &ParsedType) -> (Value, Use) {
         use ParsedTypeHead::*;
         match &ty.2 {
-            Case(..) | Func(..) | Record(..) | PolyHead(..) | PolyVar(..) | RecHead(..) | VarJoin(..) => {
+            Alias(..) | Case(..) | Func(..) | Record(..) | PolyHead(..) | PolyVar(..) | RecHead(..) | VarJoin(..) => {
                 let vredirect = self.core.val_placeholder();
                 let uredirect = self.core.use_placeholder();
                 let ph = (vredirect, uredirect);
@@ -266,6 +285,17 @@ impl<'a> TreeMaterializer<'a> {
         t
     }
 
+    fn materialize_bounds(&mut self, bounds: &ParsedBounds) -> Box<[ParamBounds]> {
+        bounds
+            .iter()
+            .map(|(name, upper, lower)| ParamBounds {
+                name: *name,
+                upper: upper.as_deref().map(|ty| self.materialize_tree(ty)),
+                lower: lower.as_deref().map(|ty| self.materialize_tree(ty)),
+            })
+            .collect()
+    }
+
     pub fn add_type(&mut self, parsed: ParsedTypeSig) -> (Value, Use) {
         self.materialize_tree(&parsed.0)
     }
@@ -291,12 +321,19 @@ impl<'a> TreeMaterializer<'a> {
 
         let mut new_types = HashMap::new();
         // Now see if we have to instantiate type parameters to local abstract types
-        for spec in parsed.poly_heads {
+        for (spec, bounds) in parsed.poly_heads {
+            let bounds = self.materialize_bounds(&bounds);
             let subs = spec
                 .params
                 .iter()
                 .copied()
-                .map(|(name, span)| (name, self.core.add_abstract_type(name, span, bindings.scopelvl)))
+                .map(|(name, span)| {
+                    let b = bounds.iter().find(|b| b.name == name);
+                    let upper = b.and_then(|b| b.upper).map(|(v, _)| v);
+                    let lower = b.and_then(|b| b.lower).map(|(_, u)| u);
+                    let ty = self.core.add_abstract_type(name, span, bindings.scopelvl, upper, lower);
+                    (name, ty)
+                })
                 .collect();
 
             let mut ctx = InstantionContext::new(self.core, Substitutions::Abs(&subs), spec.loc);
@@ -348,7 +385,7 @@ impl<'a> TreeMaterializer<'a> {
 struct ParsedBindings {
     vars: HashMap<StringId, (Span, RcParsedType)>,
     types: Vec<(StringId, SourceLoc, StringId)>,
-    poly_heads: Vec<Rc<PolyHeadData>>,
+    poly_heads: Vec<(Rc<PolyHeadData>, ParsedBounds)>,
 }
 impl ParsedBindings {
     fn insert_var(&mut self, name: StringId, span: Span, ty: RcParsedType) -> Result<()> {
@@ -525,6 +562,7 @@ impl<'a> TypeParser<'a> {
             }
             &Poly(ref params, ref def, kind) => {
                 let loc = SourceLoc(span);
+                let bounds = self.parse_param_bounds(params)?;
                 let mark = self.local_types.unwind_point();
                 self.join_allowed.insert(
                     loc,
@@ -535,7 +573,7 @@ impl<'a> TypeParser<'a> {
                 );
 
                 let mut parsed_params = HashMap::new();
-                for param in params.iter().copied() {
+                for param in params.iter() {
                     parsed_params.insert(param.name.0, param.name.1);
                     self.local_types
                         .insert(param.alias.0, TypeVar::Param(VarSpec { loc, name: param.name.0 }));
@@ -552,7 +590,7 @@ impl<'a> TypeParser<'a> {
                     loc,
                     params: parsed_params.into_iter().collect(),
                 });
-                ParsedTypeHead::PolyHead(spec, sub)
+                ParsedTypeHead::PolyHead(spec, bounds, sub)
             }
             &RecursiveDef(name, ref def) => {
                 let loc = SourceLoc(span);
@@ -598,19 +636,46 @@ impl<'a> TypeParser<'a> {
         Ok(ParsedTypeSig(self.parse_type_or_hole_sub(tyexpr, span_before_hole)?))
     }
 
+    /// Parse the bounds of the given type parameters. This must be done before the parameters are
+    /// in scope, since bounds can't refer to the parameters they constrain.
+    fn parse_param_bounds(&mut self, ty_params: &[TypeParam]) -> Result<ParsedBounds> {
+        let mut parse_bound = |ty: &Option<ast::STypeExpr>| -> Result<Option<RcParsedType>> {
+            let Some(ty) = ty else {
+                return Ok(None);
+            };
+            let parsed = self.parse_type_sub_invariant(ty)?;
+            if !parsed.0.poly.0.is_empty() || !parsed.0.rec.0.is_empty() {
+                return Err(SyntaxError::new1(
+                    "SyntaxError: Type parameter bounds may not refer to type parameters or recursive types",
+                    ty.1,
+                ));
+            }
+            Ok(Some(parsed))
+        };
+
+        let mut bounds = Vec::new();
+        for param in ty_params {
+            if param.upper.is_some() || param.lower.is_some() {
+                bounds.push((param.name.0, parse_bound(&param.upper)?, parse_bound(&param.lower)?));
+            }
+        }
+        Ok(bounds.into())
+    }
+
     fn add_type_params(
         &mut self,
         loc: SourceLoc,
         ty_params: &[TypeParam],
         kind: ast::PolyKind,
         out: &mut ParsedBindings,
-    ) -> Option<Rc<PolyHeadData>> {
+    ) -> Result<Option<(Rc<PolyHeadData>, ParsedBounds)>> {
         if ty_params.is_empty() {
-            return None;
+            return Ok(None);
         }
 
+        let bounds = self.parse_param_bounds(ty_params)?;
         let mut parsed_params = HashMap::new();
-        for param in ty_params.iter().copied() {
+        for param in ty_params.iter() {
             let (name, name_span) = param.name;
             let (alias, alias_span) = param.alias;
 
@@ -624,8 +689,8 @@ impl<'a> TypeParser<'a> {
             loc,
             params: parsed_params.into_iter().collect(),
         });
-        out.poly_heads.push(spec.clone());
-        Some(spec)
+        out.poly_heads.push((spec.clone(), bounds.clone()));
+        Ok(Some((spec, bounds)))
     }
 
     fn parse_let_pattern_sub(
@@ -637,6 +702,14 @@ impl<'a> TypeParser<'a> {
         use ast::LetPattern::*;
 
         Ok(match pat {
+            &Alias(ref sub_pat, (name, span), ref tyexpr) => {
+                let mut deps = PolyAndRecDeps::default();
+                let sub = deps.add(self.parse_let_pattern_sub(sub_pat, out, no_typed_var_allowed)?);
+                let ty = deps.add(self.parse_type_or_hole_sub(tyexpr.as_ref(), span)?);
+                out.insert_var(name, span, ty.clone())?;
+
+                Rc::new((deps, span, ParsedTypeHead::Alias(ty, sub)))
+            }
             &Var((name, span), ref tyexpr) => {
                 let ty = if let Some(tyexpr) = tyexpr.as_ref() {
                     self.parse_type_sub(tyexpr)?
@@ -674,7 +747,7 @@ impl<'a> TypeParser<'a> {
                 let loc = SourceLoc(span);
                 let mark = self.local_types.unwind_point();
 
-                let poly_spec = self.add_type_params(loc, ty_params, ast::PolyKind::Existential, out);
+                let poly_spec = self.add_type_params(loc, ty_params, ast::PolyKind::Existential, out)?;
 
                 let mut field_names = HashMap::with_capacity(pairs.len());
                 let mut fields = HashMap::new();
@@ -695,9 +768,9 @@ impl<'a> TypeParser<'a> {
                 self.local_types.unwind(mark);
 
                 let mut new_type = Rc::new((deps.clone(), span, ParsedTypeHead::Record(fields)));
-                if let Some(spec) = poly_spec {
+                if let Some((spec, bounds)) = poly_spec {
                     deps.poly.remove(loc);
-                    new_type = Rc::new((deps, span, ParsedTypeHead::PolyHead(spec, new_type)));
+                    new_type = Rc::new((deps, span, ParsedTypeHead::PolyHead(spec, bounds, new_type)));
                 }
 
                 new_type
@@ -725,7 +798,7 @@ impl<'a> TypeParser<'a> {
         let mut out = ParsedBindings::default();
 
         let mark = self.local_types.unwind_point();
-        let poly_spec = self.add_type_params(loc, ty_params, ast::PolyKind::Universal, &mut out);
+        let poly_spec = self.add_type_params(loc, ty_params, ast::PolyKind::Universal, &mut out)?;
 
         let mut deps = PolyAndRecDeps::default();
         let arg_bound = deps.add(self.parse_let_pattern_sub(arg_pat, &mut out, true)?);
@@ -735,8 +808,12 @@ impl<'a> TypeParser<'a> {
 
         let mut func_type = Rc::new((deps, span, ParsedTypeHead::Func(arg_bound, ret_type.clone())));
 
-        if let Some(spec) = poly_spec {
-            func_type = Rc::new((PolyAndRecDeps::default(), span, ParsedTypeHead::PolyHead(spec, func_type)));
+        if let Some((spec, bounds)) = poly_spec {
+            func_type = Rc::new((
+                PolyAndRecDeps::default(),
+                span,
+                ParsedTypeHead::PolyHead(spec, bounds, func_type),
+            ));
         }
 
         Ok(ParsedFuncSig {
@@ -1,77 +1,94 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::hash::Hasher;
 
-#[derive(Debug, Clone)]
-struct OrderedMap<K, V> {
-    keys: Vec<K>,
-    m: HashMap<K, V>,
-}
-impl<K: Eq + std::hash::Hash + Clone, V> OrderedMap<K, V> {
-    fn new() -> Self {
-        Self {
-            keys: Vec::new(),
-            m: HashMap::new(),
+// Multiplicative hash for node indices. This is much faster than the default hasher
+// and doesn't need to be resistant to collision attacks since indices aren't user controlled.
+#[derive(Default)]
+struct IndexHasher(u64);
+impl Hasher for IndexHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b.into());
         }
     }
 
-    fn insert(&mut self, k: K, v: V) -> Option<V> {
-        let old = self.m.insert(k.clone(), v);
-        if old.is_none() {
-            self.keys.push(k);
-        }
-        old
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x517cc1b727220a95);
     }
 
-    fn iter_keys(&self) -> std::slice::Iter<K> {
-        self.keys.iter()
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
     }
 
-    fn retain(&mut self, f: impl Fn(&K) -> bool) {
-        self.m.retain(|k, _| f(k));
-        // Also remove any extra keys left from unrelated deletions
-        // since journal removal may have left extra keys in the list.
-        self.keys.retain(|k| self.m.contains_key(k));
+    fn finish(&self) -> u64 {
+        self.0
     }
 }
 
 pub trait ExtNodeDataTrait {
     fn truncate(&mut self, i: TypeNodeInd);
+    fn renumber(&mut self, f: &dyn Fn(TypeNodeInd) -> TypeNodeInd);
 }
 
 pub trait EdgeDataTrait<ExtNodeData>: Clone {
     fn update(&mut self, other: &Self) -> bool;
     fn expand(self, hole: &ExtNodeData, ind: TypeNodeInd) -> Self;
+    fn renumber(&mut self, f: &dyn Fn(TypeNodeInd) -> TypeNodeInd);
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TypeNodeInd(pub usize);
 
-struct ReachabilityNode<ExtNodeData, ExtEdgeData> {
+struct ReachabilityNode<ExtNodeData> {
     data: ExtNodeData,
-    flows_from: OrderedMap<TypeNodeInd, ExtEdgeData>,
-    flows_to: OrderedMap<TypeNodeInd, ExtEdgeData>,
+    // Edges are listed in the order they were added
+    flows_from: Vec<TypeNodeInd>,
+    flows_to: Vec<TypeNodeInd>,
 }
-impl<N: ExtNodeDataTrait, E> ReachabilityNode<N, E> {
-    fn fix_and_truncate(&mut self, i: TypeNodeInd) {
-        self.data.truncate(i);
-        self.flows_from.retain(|&k| k < i);
-        self.flows_to.retain(|&k| k < i);
-    }
+
+struct Edge<ExtEdgeData> {
+    lhs: TypeNodeInd,
+    rhs: TypeNodeInd,
+    data: ExtEdgeData,
+}
+
+// Changes to nodes and edges before the rewind mark, which must be undone on revert
+enum JournalEntry<ExtEdgeData> {
+    // The value of an edge before the mark was changed
+    Updated(usize, ExtEdgeData),
+    // The node data was changed to refer to nodes after the mark
+    DataModified(TypeNodeInd),
 }
 
 pub struct Reachability<ExtNodeData, ExtEdgeData> {
-    nodes: Vec<ReachabilityNode<ExtNodeData, ExtEdgeData>>,
+    nodes: Vec<ReachabilityNode<ExtNodeData>>,
+    // Each edge value is stored once here, and referred to by index from edge_ids
+    edges: Vec<Edge<ExtEdgeData>>,
+    edge_ids: HashMap<(TypeNodeInd, TypeNodeInd), usize, BuildHasherDefault<IndexHasher>>,
 
     // Nodes past this point may be reverted in case of a type error
     // Value of 0 indicates no mark is set (or if a mark is set, there's nothing to do anyway)
     rewind_mark: TypeNodeInd,
-    journal: Vec<(TypeNodeInd, TypeNodeInd, Option<ExtEdgeData>)>,
+    // Edges past this point will be removed on revert
+    edge_mark: usize,
+    // Reverting replays this in reverse, so the cost of a revert is proportional to
+    // the number of changes made since the mark rather than the size of the graph.
+    journal: Vec<JournalEntry<ExtEdgeData>>,
+    // Marks and journal length of enclosing saves, when saves are nested
+    outer_saves: Vec<(TypeNodeInd, usize, usize)>,
 }
 impl<ExtNodeData: ExtNodeDataTrait, ExtEdgeData: EdgeDataTrait<ExtNodeData>> Reachability<ExtNodeData, ExtEdgeData> {
     pub fn new() -> Self {
         Self {
             nodes: Vec::new(),
+            edges: Vec::new(),
+            edge_ids: HashMap::default(),
             rewind_mark: TypeNodeInd(0),
+            edge_mark: 0,
             journal: Vec::new(),
+            outer_saves: Vec::new(),
         }
     }
 
@@ -86,7 +103,16 @@ impl<ExtNodeData: ExtNodeDataTrait, ExtEdgeData: EdgeDataTrait<ExtNodeData>> Rea
         self.nodes.get_mut(i.0).map(|rn| &mut rn.data)
     }
     pub fn get_edge(&self, lhs: TypeNodeInd, rhs: TypeNodeInd) -> Option<&ExtEdgeData> {
-        self.nodes.get(lhs.0).and_then(|rn| rn.flows_to.m.get(&rhs))
+        self.edge_ids.get(&(lhs, rhs)).map(|&id| &self.edges[id].data)
+    }
+
+    /// Nodes with a (possibly transitive) edge to the given node
+    pub fn flows_from(&self, i: TypeNodeInd) -> impl Iterator<Item = TypeNodeInd> + '_ {
+        self.nodes[i.0].flows_from.iter().copied()
+    }
+    /// Nodes with a (possibly transitive) edge from the given node
+    pub fn flows_to(&self, i: TypeNodeInd) -> impl Iterator<Item = TypeNodeInd> + '_ {
+        self.nodes[i.0].flows_to.iter().copied()
     }
 
     pub fn add_node(&mut self, data: ExtNodeData) -> TypeNodeInd {
@@ -94,21 +120,25 @@ impl<ExtNodeData: ExtNodeDataTrait, ExtEdgeData: EdgeDataTrait<ExtNodeData>> Rea
 
         let n = ReachabilityNode {
             data,
-            flows_from: OrderedMap::new(),
-            flows_to: OrderedMap::new(),
+            flows_from: Vec::new(),
+            flows_to: Vec::new(),
         };
         self.nodes.push(n);
         TypeNodeInd(i)
     }
 
-    fn update_edge_value(&mut self, lhs: TypeNodeInd, rhs: TypeNodeInd, val: ExtEdgeData) {
-        let old = self.nodes[lhs.0].flows_to.insert(rhs, val.clone());
-        self.nodes[rhs.0].flows_from.insert(lhs, val);
+    fn insert_edge(&mut self, lhs: TypeNodeInd, rhs: TypeNodeInd, data: ExtEdgeData) {
+        self.edge_ids.insert((lhs, rhs), self.edges.len());
+        self.edges.push(Edge { lhs, rhs, data });
+        self.nodes[lhs.0].flows_to.push(rhs);
+        self.nodes[rhs.0].flows_from.push(lhs);
+    }
 
-        // If the nodes are >= rewind_mark, they'll be removed during rewind anyway
-        // so we only have to journal edge values when both are below the mark.
-        if lhs < self.rewind_mark && rhs < self.rewind_mark {
-            self.journal.push((lhs, rhs, old));
+    /// Record that the data for the given node was changed to refer to new nodes, so
+    /// that those references can be removed if the changes are reverted.
+    pub fn mark_data_modified(&mut self, i: TypeNodeInd) {
+        if i < self.rewind_mark {
+            self.journal.push(JournalEntry::DataModified(i));
         }
     }
 
@@ -120,34 +150,48 @@ impl<ExtNodeData: ExtNodeDataTrait, ExtEdgeData: EdgeDataTrait<ExtNodeData>> Rea
         out: &mut Vec<(TypeNodeInd, TypeNodeInd, ExtEdgeData)>,
     ) {
         // println!("add_edge {}->{}", lhs.0, rhs.0);
-        let mut work = vec![(lhs, rhs, edge_val)];
+        // Work items refer to values in vals, so that a value propagated to many
+        // neighboring nodes is only cloned for the edges that actually change.
+        let mut vals = vec![edge_val];
+        let mut work = vec![(lhs, rhs, 0)];
 
-        while let Some((lhs, rhs, mut edge_val)) = work.pop() {
+        while let Some((lhs, rhs, val_ind)) = work.pop() {
             // println!("    add_edge_sub {}->{}", lhs.0, rhs.0);
-            let old_edge = self.nodes[lhs.0].flows_to.m.get_mut(&rhs);
-            match old_edge {
-                Some(old) => {
-                    let mut old = old.clone();
-                    if old.update(&edge_val) {
-                        // println!("reevaluating {} {}", lhs.0, rhs.0);
-                        edge_val = old; // updated value will be inserted into map below
-                    } else {
+            let edge_val = match self.edge_ids.get(&(lhs, rhs)) {
+                Some(&id) => {
+                    let old = &mut self.edges[id].data;
+                    // If the edge is >= edge_mark, it will be removed during rewind anyway
+                    // so we only have to journal the previous value of older edges.
+                    if id < self.edge_mark {
+                        let prev = old.clone();
+                        if !old.update(&vals[val_ind]) {
+                            continue;
+                        }
+                        self.journal.push(JournalEntry::Updated(id, prev));
+                    } else if !old.update(&vals[val_ind]) {
                         // New edge value did not cause an update compared to existing edge value.
                         continue;
                     }
+                    // println!("reevaluating {} {}", lhs.0, rhs.0);
+                    old.clone()
+                }
+                None => {
+                    let val = vals[val_ind].clone();
+                    self.insert_edge(lhs, rhs, val.clone());
+                    val
                 }
-                None => {}
             };
-            self.update_edge_value(lhs, rhs, edge_val.clone());
 
-            let temp = edge_val.clone().expand(&self.nodes[lhs.0].data, lhs);
-            for &lhs2 in self.nodes[lhs.0].flows_from.iter_keys() {
-                work.push((lhs2, rhs, temp.clone()));
+            let lhs_node = &self.nodes[lhs.0];
+            if !lhs_node.flows_from.is_empty() {
+                vals.push(edge_val.clone().expand(&lhs_node.data, lhs));
+                work.extend(lhs_node.flows_from.iter().map(|&lhs2| (lhs2, rhs, vals.len() - 1)));
             }
 
-            let temp = edge_val.clone().expand(&self.nodes[rhs.0].data, rhs);
-            for &rhs2 in self.nodes[rhs.0].flows_to.iter_keys() {
-                work.push((lhs, rhs2, temp.clone()));
+            let rhs_node = &self.nodes[rhs.0];
+            if !rhs_node.flows_to.is_empty() {
+                vals.push(edge_val.clone().expand(&rhs_node.data, rhs));
+                work.extend(rhs_node.flows_to.iter().map(|&rhs2| (lhs, rhs2, vals.len() - 1)));
             }
 
             // Inform the caller that a new edge was added
@@ -155,35 +199,108 @@ impl<ExtNodeData: ExtNodeDataTrait, ExtEdgeData: EdgeDataTrait<ExtNodeData>> Rea
         }
     }
 
+    /// Start recording changes so they can be reverted. Saves may be nested, in which case
+    /// revert and make_permanent apply to the innermost save.
     pub fn save(&mut self) {
-        assert!(self.rewind_mark.0 == 0);
+        if self.rewind_mark.0 != 0 {
+            self.outer_saves.push((self.rewind_mark, self.edge_mark, self.journal.len()));
+        }
         self.rewind_mark = TypeNodeInd(self.nodes.len());
+        self.edge_mark = self.edges.len();
     }
 
     pub fn revert(&mut self) {
         let i = self.rewind_mark;
-        self.rewind_mark = TypeNodeInd(0);
+        let (outer_mark, outer_edge_mark, journal_len) = self.outer_saves.pop().unwrap_or((TypeNodeInd(0), 0, 0));
+        self.rewind_mark = outer_mark;
         self.nodes.truncate(i.0);
 
-        while let Some((lhs, rhs, val)) = self.journal.pop() {
-            if let Some(val) = val {
-                *self.nodes[lhs.0].flows_to.m.get_mut(&rhs).unwrap() = val.clone();
-                *self.nodes[rhs.0].flows_from.m.get_mut(&lhs).unwrap() = val;
-            } else {
-                self.nodes[lhs.0].flows_to.m.remove(&rhs);
-                self.nodes[rhs.0].flows_from.m.remove(&lhs);
+        while self.journal.len() > journal_len {
+            match self.journal.pop().unwrap() {
+                JournalEntry::Updated(id, val) => self.edges[id].data = val,
+                // Entries from a nested save that was made permanent may refer to nodes that are already gone
+                JournalEntry::DataModified(n) if n < i => self.nodes[n.0].data.truncate(i),
+                JournalEntry::DataModified(_) => {}
             }
         }
 
-        // If we removed edges above, the edge maps will have extra keys
-        // fix_and_truncate will fix that in addition to truncating edges >= i
-        for n in self.nodes.iter_mut() {
-            n.fix_and_truncate(i);
+        // Edges are always appended to the lists of the nodes they connect, so removing
+        // new edges in reverse order just pops them off the end of the lists again.
+        for edge in self.edges.drain(self.edge_mark..).rev() {
+            self.edge_ids.remove(&(edge.lhs, edge.rhs));
+            if edge.lhs < i {
+                let last = self.nodes[edge.lhs.0].flows_to.pop();
+                assert!(last == Some(edge.rhs));
+            }
+            if edge.rhs < i {
+                let last = self.nodes[edge.rhs.0].flows_from.pop();
+                assert!(last == Some(edge.lhs));
+            }
         }
+        self.edge_mark = outer_edge_mark;
     }
 
     pub fn make_permanent(&mut self) {
+        // For nested saves, the changes still have to be undone if the enclosing save is reverted
+        if let Some((outer_mark, outer_edge_mark, _)) = self.outer_saves.pop() {
+            self.rewind_mark = outer_mark;
+            self.edge_mark = outer_edge_mark;
+            return;
+        }
         self.rewind_mark = TypeNodeInd(0);
+        self.edge_mark = 0;
         self.journal.clear();
     }
+
+    /// Remove every node not in keep along with its edges, and renumber the remaining nodes
+    /// in their original order. Returns the new index for each old node that was kept.
+    /// Indices that don't refer to a node (i.e. >= len) are passed through unchanged.
+    pub fn compact(&mut self, keep: &HashSet<TypeNodeInd>) -> Vec<Option<TypeNodeInd>> {
+        assert!(self.rewind_mark.0 == 0 && self.journal.is_empty());
+
+        let mut mapping = vec![None; self.nodes.len()];
+        let mut count = 0;
+        for (i, new) in mapping.iter_mut().enumerate() {
+            if keep.contains(&TypeNodeInd(i)) {
+                *new = Some(TypeNodeInd(count));
+                count += 1;
+            }
+        }
+
+        let f = |i: TypeNodeInd| match mapping.get(i.0) {
+            Some(new) => new.expect("Kept node refers to removed node"),
+            None => i,
+        };
+        let renumber_list = |old: Vec<TypeNodeInd>| old.into_iter().filter_map(|k| mapping[k.0]).collect();
+
+        let old_nodes = std::mem::take(&mut self.nodes);
+        for (i, node) in old_nodes.into_iter().enumerate() {
+            if mapping[i].is_none() {
+                continue;
+            }
+            let ReachabilityNode {
+                mut data,
+                flows_from,
+                flows_to,
+            } = node;
+            data.renumber(&f);
+            self.nodes.push(ReachabilityNode {
+                data,
+                flows_from: renumber_list(flows_from),
+                flows_to: renumber_list(flows_to),
+            });
+        }
+
+        let old_edges = std::mem::take(&mut self.edges);
+        self.edge_ids.clear();
+        for edge in old_edges {
+            if let (Some(lhs), Some(rhs)) = (mapping[edge.lhs.0], mapping[edge.rhs.0]) {
+                let mut data = edge.data;
+                data.renumber(&f);
+                self.edge_ids.insert((lhs, rhs), self.edges.len());
+                self.edges.push(Edge { lhs, rhs, data });
+            }
+        }
+        mapping
+    }
 }
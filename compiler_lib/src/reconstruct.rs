@@ -0,0 +1,868 @@
+use std::collections::HashMap;
+
+use crate::ast;
+use crate::ast::PolyKind;
+use crate::core::*;
+use crate::reachability::TypeNodeInd;
+use crate::spans::Span;
+
+// Simplified type trees reconstructed from the type graph, used for displaying inferred types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Ty {
+    Never,
+    Any,
+    // Inference variables without any bounds yet
+    Hole,
+    Abstract(String),
+    Var(String),
+    Func(Box<Ty>, Box<Ty>),
+    // Fields are (name, read type, write type)
+    Record(Vec<(String, Ty, Option<Ty>)>),
+    Case(Vec<(String, Ty)>),
+    Union(Vec<Ty>),
+    Intersect(Vec<Ty>),
+    // Type parameters are (name, upper bound, lower bound)
+    Poly(PolyKind, Vec<(String, Option<Ty>, Option<Ty>)>, Box<Ty>),
+    Rec(String, Box<Ty>),
+    // Parts of the type beyond the size limit, printed as ...
+    Elided,
+}
+
+fn union(tys: Vec<Ty>) -> Ty {
+    join(tys, true)
+}
+fn intersect(tys: Vec<Ty>) -> Ty {
+    join(tys, false)
+}
+
+// Simplify unions and intersections by merging types with the same type constructor,
+// following the rules described in the readme. Types with different type constructors
+// are left as an explicit union or intersection.
+fn join(tys: Vec<Ty>, is_union: bool) -> Ty {
+    let (identity, absorbing) = if is_union {
+        (Ty::Never, Ty::Any)
+    } else {
+        (Ty::Any, Ty::Never)
+    };
+
+    let mut parts: Vec<Ty> = Vec::new();
+    for ty in tys {
+        let flattened = match ty {
+            Ty::Union(v) if is_union => v,
+            Ty::Intersect(v) if !is_union => v,
+            ty => vec![ty],
+        };
+
+        for ty in flattened {
+            if ty == absorbing {
+                return absorbing;
+            } else if ty == identity || ty == Ty::Hole {
+                continue;
+            }
+
+            if let Some(i) = parts.iter().position(|part| can_merge(part, &ty)) {
+                let old = std::mem::replace(&mut parts[i], Ty::Hole);
+                parts[i] = merge(old, ty, is_union);
+            } else {
+                parts.push(ty);
+            }
+        }
+    }
+
+    match parts.len() {
+        0 => identity,
+        1 => parts.pop().unwrap(),
+        _ if is_union => Ty::Union(parts),
+        _ => Ty::Intersect(parts),
+    }
+}
+
+fn can_merge(lhs: &Ty, rhs: &Ty) -> bool {
+    use Ty::*;
+    lhs == rhs
+        || matches!(
+            (lhs, rhs),
+            (Func(..), Func(..)) | (Record(..), Record(..)) | (Case(..), Case(..))
+        )
+}
+
+fn merge(lhs: Ty, rhs: Ty, is_union: bool) -> Ty {
+    use Ty::*;
+    match (lhs, rhs) {
+        (Func(arg1, ret1), Func(arg2, ret2)) => {
+            // Arguments are contravariant
+            let arg = join(vec![*arg1, *arg2], !is_union);
+            let ret = join(vec![*ret1, *ret2], is_union);
+            Func(Box::new(arg), Box::new(ret))
+        }
+        (Record(fields1), Record(fields2)) => {
+            let mut fields2: HashMap<_, _> = fields2.into_iter().map(|(name, r, w)| (name, (r, w))).collect();
+            let mut fields = Vec::new();
+            for (name, r1, w1) in fields1 {
+                match fields2.remove(&name) {
+                    Some((r2, w2)) => {
+                        let r = join(vec![r1, r2], is_union);
+                        let w = match (w1, w2) {
+                            (Some(w1), Some(w2)) => Some(join(vec![w1, w2], !is_union)),
+                            (w1, w2) if !is_union => w1.or(w2),
+                            _ => None,
+                        };
+                        fields.push((name, r, w));
+                    }
+                    // The union of records only has the common fields, while the intersection has all of them
+                    None if !is_union => fields.push((name, r1, w1)),
+                    None => {}
+                }
+            }
+            if !is_union {
+                fields.extend(fields2.into_iter().map(|(name, (r, w))| (name, r, w)));
+            }
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Record(fields)
+        }
+        (Case(cases1), Case(cases2)) => {
+            let mut cases2: HashMap<_, _> = cases2.into_iter().collect();
+            let mut cases = Vec::new();
+            for (tag, ty1) in cases1 {
+                match cases2.remove(&tag) {
+                    Some(ty2) => cases.push((tag, join(vec![ty1, ty2], is_union))),
+                    // The union of variants has all of the tags, while the intersection has only the common ones
+                    None if is_union => cases.push((tag, ty1)),
+                    None => {}
+                }
+            }
+            if is_union {
+                cases.extend(cases2);
+            }
+            cases.sort_by(|a, b| a.0.cmp(&b.0));
+            if cases.is_empty() { Never } else { Case(cases) }
+        }
+        (lhs, _) => lhs,
+    }
+}
+
+// Maximum number of type nodes visited when reconstructing a single type. Types can share
+// subtrees, so the expanded tree can be exponentially larger than the type graph.
+const MAX_VISITED_NODES: usize = 1000;
+
+struct Reconstructor<'a> {
+    core: &'a TypeCheckerCore,
+    strings: &'a lasso::Rodeo,
+    // Number of nodes that can still be visited before the rest of the type is elided
+    budget: usize,
+    // Nodes currently being visited (along with polarity) and the name
+    // assigned to them if a recursive reference was encountered.
+    in_progress: HashMap<(TypeNodeInd, bool), Option<String>>,
+    rec_count: usize,
+
+    // When generalizing a function, inference variables at or after this index were created
+    // while checking the function. Each time one is visited, it is recorded along with its polarity.
+    local_start: usize,
+    local_vars: Vec<(TypeNodeInd, bool)>,
+    // Local inference variables which are printed as type parameters
+    params: HashMap<TypeNodeInd, String>,
+
+    // When printing an interface, holes and elided parts of the type are replaced by
+    // concrete types that are consistent with the type graph.
+    interface: bool,
+}
+impl<'a> Reconstructor<'a> {
+    fn name(&self, name: crate::ast::StringId) -> String {
+        self.strings.resolve(&name).to_owned()
+    }
+
+    fn new(core: &'a TypeCheckerCore, strings: &'a lasso::Rodeo) -> Self {
+        Self {
+            core,
+            strings,
+            budget: MAX_VISITED_NODES,
+            in_progress: HashMap::new(),
+            rec_count: 0,
+            local_start: usize::MAX,
+            local_vars: Vec::new(),
+            params: HashMap::new(),
+            interface: false,
+        }
+    }
+
+    fn visit(&mut self, i: TypeNodeInd, positive: bool) -> Ty {
+        if i == NONE {
+            return if positive { Ty::Never } else { Ty::Any };
+        }
+        if let Some(name) = self.params.get(&i) {
+            return Ty::Var(name.clone());
+        }
+        if self.budget == 0 {
+            return match (self.interface, positive) {
+                (false, _) => Ty::Elided,
+                (true, true) => Ty::Any,
+                (true, false) => Ty::Never,
+            };
+        }
+        self.budget -= 1;
+
+        let key = (i, positive);
+        if let Some(name) = self.in_progress.get(&key) {
+            let name = match name {
+                Some(name) => name.clone(),
+                None => {
+                    self.rec_count += 1;
+                    let name = format!("t{}", self.rec_count);
+                    self.in_progress.insert(key, Some(name.clone()));
+                    name
+                }
+            };
+            return Ty::Var(name);
+        }
+        self.in_progress.insert(key, None);
+
+        let core = self.core;
+        let ty = match core.r.get(i).unwrap() {
+            TypeNode::Var(_) => {
+                let is_local = i.0 >= self.local_start;
+                if is_local {
+                    self.local_vars.push((i, positive));
+                }
+                match self.var_bounds(i, positive) {
+                    // A function doesn't constrain unused local variables at all
+                    Ty::Hole if is_local && positive => Ty::Never,
+                    Ty::Hole if is_local => Ty::Any,
+                    Ty::Hole if self.interface => self.interface_hole(i, positive),
+                    ty => ty,
+                }
+            }
+            TypeNode::Value((head, ..)) => self.visit_val_head(head),
+            TypeNode::Use((head, ..)) => self.visit_use_head(head),
+            TypeNode::Placeholder => unreachable!(),
+        };
+
+        match self.in_progress.remove(&key).unwrap() {
+            Some(name) => Ty::Rec(name, Box::new(ty)),
+            None => ty,
+        }
+    }
+
+    // An inference variable with no bounds in the given polarity, for an interface. Any is always
+    // a valid upper bound for the values flowing out of it. Values flowing in must be accepted by
+    // its uses, so only never is safe there, unless it and every variable connected to it are
+    // completely unconstrained, in which case any value could flow through.
+    fn interface_hole(&self, i: TypeNodeInd, positive: bool) -> Ty {
+        if positive || self.is_unconstrained(i) {
+            Ty::Any
+        } else {
+            Ty::Never
+        }
+    }
+
+    fn is_unconstrained(&self, i: TypeNodeInd) -> bool {
+        let core = self.core;
+        let mut seen = vec![i];
+        let mut todo = vec![i];
+        while let Some(v) = todo.pop() {
+            for j in core.r.flows_from(v).chain(core.r.flows_to(v)) {
+                if !matches!(core.r.get(j), Some(TypeNode::Var(_))) {
+                    return false;
+                }
+                if !seen.contains(&j) {
+                    seen.push(j);
+                    todo.push(j);
+                }
+            }
+        }
+        true
+    }
+
+    // Reachability is transitively closed, so the bounds of an inference variable
+    // are just the value (or use) heads directly connected to it.
+    fn var_bounds(&mut self, i: TypeNodeInd, positive: bool) -> Ty {
+        let core = self.core;
+        let bounds: Vec<_> = if positive {
+            core.r
+                .flows_from(i)
+                .filter(|&j| matches!(core.r.get(j), Some(TypeNode::Value(..))))
+                .collect()
+        } else {
+            core.r
+                .flows_to(i)
+                .filter(|&j| matches!(core.r.get(j), Some(TypeNode::Use(..))))
+                .collect()
+        };
+
+        if bounds.is_empty() {
+            Ty::Hole
+        } else {
+            let tys = bounds.into_iter().map(|j| self.visit(j, positive)).collect();
+            if positive { union(tys) } else { intersect(tys) }
+        }
+    }
+
+    fn visit_val_head(&mut self, head: &VTypeHead) -> Ty {
+        use VTypeHead::*;
+        match head {
+            VUnion(vals) => union(vals.iter().map(|v| self.visit(v.0, true)).collect()),
+            VInstantiateExist { target, .. } => self.visit(target.0, true),
+            VTop => Ty::Any,
+            VFunc { arg, ret } => {
+                let arg = self.visit(arg.0, false);
+                let ret = self.visit(ret.0, true);
+                Ty::Func(Box::new(arg), Box::new(ret))
+            }
+            VObj { fields } => {
+                // Visit fields in order so that if the type is too large, the last fields are elided
+                let mut sorted: Vec<_> = fields.iter().map(|(&name, &(r, w, _))| (self.name(name), r, w)).collect();
+                sorted.sort_by(|a, b| a.0.cmp(&b.0));
+                let fields = sorted
+                    .into_iter()
+                    .map(|(name, r, w)| {
+                        let r = self.visit(r.0, true);
+                        let w = w.map(|w| self.visit(w.0, false));
+                        (name, r, w)
+                    })
+                    .collect();
+                Ty::Record(fields)
+            }
+            &VCase { case: (tag, val) } => Ty::Case(vec![(self.name(tag), self.visit(val.0, true))]),
+            &VAbstract { ty } => Ty::Abstract(self.name(self.core.type_ctors[ty.0].name)),
+            VPolyHead(data, sub, _, bounds) => self.visit_poly(data, bounds, sub.0, true),
+            VTypeVar(spec) => Ty::Var(self.name(spec.name)),
+            VDisjointIntersect(vars, default) => {
+                let mut tys = self.type_vars(vars);
+                tys.extend(default.map(|v| self.visit(v.0, true)));
+                intersect(tys)
+            }
+        }
+    }
+
+    fn visit_use_head(&mut self, head: &UTypeHead) -> Ty {
+        use UTypeHead::*;
+        match head {
+            UIntersection(uses) => intersect(uses.iter().map(|u| self.visit(u.0, false)).collect()),
+            UInstantiateUni { target, .. } => self.visit(target.0, false),
+            UBot => Ty::Never,
+            UFunc { arg, ret } => {
+                let arg = self.visit(arg.0, true);
+                let ret = self.visit(ret.0, false);
+                Ty::Func(Box::new(arg), Box::new(ret))
+            }
+            UObj { fields } => {
+                // Visit fields in order so that if the type is too large, the last fields are elided
+                let mut sorted: Vec<_> = fields.iter().map(|(&name, &(r, w, _))| (self.name(name), r, w)).collect();
+                sorted.sort_by(|a, b| a.0.cmp(&b.0));
+                let fields = sorted
+                    .into_iter()
+                    .map(|(name, r, w)| {
+                        let r = self.visit(r.0, false);
+                        let w = w.map(|w| self.visit(w.0, true));
+                        (name, r, w)
+                    })
+                    .collect();
+                Ty::Record(fields)
+            }
+            UCase { cases, wildcard } => {
+                let mut cases: Vec<_> = cases
+                    .iter()
+                    .map(|(&tag, u)| (self.name(tag), self.visit(u.0, false)))
+                    .collect();
+                cases.sort_by(|a, b| a.0.cmp(&b.0));
+                let mut tys = vec![Ty::Case(cases)];
+                tys.extend(wildcard.map(|u| self.visit(u.0, false)));
+                union(tys)
+            }
+            &UAbstract { ty } => Ty::Abstract(self.name(self.core.type_ctors[ty.0].name)),
+            UOverload { cases } => union(
+                cases
+                    .iter()
+                    .map(|&(ty, _)| Ty::Abstract(self.name(self.core.type_ctors[ty.0].name)))
+                    .collect(),
+            ),
+            UPolyHead(data, sub, _, bounds) => self.visit_poly(data, bounds, sub.0, false),
+            UTypeVar(spec) => Ty::Var(self.name(spec.name)),
+            UDisjointUnion(vars, default) => {
+                let mut tys = self.type_vars(vars);
+                tys.extend(default.map(|u| self.visit(u.0, false)));
+                union(tys)
+            }
+        }
+    }
+
+    fn visit_poly(&mut self, data: &PolyHeadData, bounds: &[ParamBounds], sub: TypeNodeInd, positive: bool) -> Ty {
+        // Print parameters in the order they were declared
+        let mut sorted = data.params.to_vec();
+        sorted.sort_by_key(|&(name, span)| (span, self.strings.resolve(&name)));
+        let params = sorted
+            .into_iter()
+            .map(|(name, _)| {
+                let b = bounds.iter().find(|b| b.name == name);
+                let upper = b.and_then(|b| b.upper).map(|(v, _)| self.visit(v.0, true));
+                let lower = b.and_then(|b| b.lower).map(|(v, _)| self.visit(v.0, true));
+                (self.name(name), upper, lower)
+            })
+            .collect();
+        Ty::Poly(data.kind, params, Box::new(self.visit(sub, positive)))
+    }
+
+    fn type_vars(&self, vars: &std::collections::HashSet<VarSpec>) -> Vec<Ty> {
+        let mut names: Vec<_> = vars.iter().map(|spec| self.name(spec.name)).collect();
+        names.sort();
+        names.into_iter().map(Ty::Var).collect()
+    }
+}
+
+// Precedence levels of the type grammar
+const TOP: u8 = 0;
+const UNION: u8 = 1;
+const INTERSECT: u8 = 2;
+const TUPLE: u8 = 3;
+const SIMPLE: u8 = 4;
+
+fn tuple_fields(fields: &[(String, Ty, Option<Ty>)]) -> Option<Vec<&Ty>> {
+    if fields.len() < 2 || fields.iter().any(|f| f.2.is_some()) {
+        return None;
+    }
+    (0..fields.len())
+        .map(|i| {
+            let name = format!("_{}", i);
+            fields.iter().find(|f| f.0 == name).map(|f| &f.1)
+        })
+        .collect()
+}
+
+fn level(ty: &Ty) -> u8 {
+    use Ty::*;
+    match ty {
+        Func(..) | Rec(..) | Poly(PolyKind::Universal, ..) => TOP,
+        Union(..) => UNION,
+        Intersect(..) => INTERSECT,
+        Record(fields) if tuple_fields(fields).is_some() => TUPLE,
+        _ => SIMPLE,
+    }
+}
+
+fn print_list<'a>(out: &mut String, tys: impl IntoIterator<Item = &'a Ty>, sep: &str, lvl: u8) {
+    for (i, ty) in tys.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(sep);
+        }
+        print(out, ty, lvl);
+    }
+}
+
+fn print_fields(out: &mut String, fields: &[(String, Ty, Option<Ty>)]) {
+    for (i, (name, r, w)) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push_str("; ");
+        }
+        if w.is_some() {
+            out.push_str("mut ");
+        }
+        out.push_str(name);
+        out.push_str(": ");
+        print(out, r, TOP);
+        match w {
+            Some(w) if w != r && *w != Ty::Hole => {
+                out.push_str(" <- ");
+                print(out, w, TOP);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn print_param(out: &mut String, (name, upper, lower): &(String, Option<Ty>, Option<Ty>)) {
+    out.push_str(name);
+    if let Some(upper) = upper {
+        out.push_str(" <: ");
+        print(out, upper, UNION);
+    }
+    if let Some(lower) = lower {
+        out.push_str(" :> ");
+        print(out, lower, UNION);
+    }
+}
+
+fn print(out: &mut String, ty: &Ty, min_level: u8) {
+    use Ty::*;
+    let parens = level(ty) < min_level;
+    if parens {
+        out.push('(');
+    }
+
+    match ty {
+        Never => out.push_str("never"),
+        Any => out.push_str("any"),
+        Hole => out.push('_'),
+        Elided => out.push_str("..."),
+        Abstract(name) | Var(name) => out.push_str(name),
+        Func(arg, ret) => {
+            print(out, arg, UNION);
+            out.push_str(" -> ");
+            print(out, ret, TOP);
+        }
+        Record(fields) => {
+            if let Some(tys) = tuple_fields(fields) {
+                print_list(out, tys, " * ", SIMPLE);
+            } else {
+                out.push('{');
+                print_fields(out, fields);
+                out.push('}');
+            }
+        }
+        Case(cases) => {
+            out.push('[');
+            for (i, (tag, ty)) in cases.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" | ");
+                }
+                out.push('`');
+                out.push_str(tag);
+                out.push(' ');
+                print(out, ty, TUPLE);
+            }
+            out.push(']');
+        }
+        Union(tys) => print_list(out, tys, " | ", INTERSECT),
+        Intersect(tys) => print_list(out, tys, " & ", TUPLE),
+        Poly(kind, params, sub) => match (kind, &**sub) {
+            (PolyKind::Universal, Func(..)) => {
+                out.push_str("type");
+                for param in params {
+                    out.push(' ');
+                    print_param(out, param);
+                }
+                out.push_str(". ");
+                print(out, sub, TOP);
+            }
+            (PolyKind::Existential, Record(fields)) => {
+                out.push('{');
+                for param in params {
+                    out.push_str("type ");
+                    print_param(out, param);
+                    out.push_str("; ");
+                }
+                print_fields(out, fields);
+                out.push('}');
+            }
+            // Can't be written in PolySubML syntax, so just print the type without quantifiers
+            _ => print(out, sub, min_level),
+        },
+        Rec(name, sub) => {
+            out.push_str("rec ");
+            out.push_str(name);
+            out.push_str(" = ");
+            print(out, sub, TOP);
+        }
+    }
+
+    if parens {
+        out.push(')');
+    }
+}
+
+fn print_type(core: &TypeCheckerCore, strings: &lasso::Rodeo, i: TypeNodeInd, positive: bool) -> String {
+    let mut r = Reconstructor::new(core, strings);
+    let ty = r.visit(i, positive);
+
+    let mut out = String::new();
+    print(&mut out, &ty, TOP);
+    out
+}
+
+/// Print the inferred type of the given value in PolySubML type syntax
+pub fn print_value_type(core: &TypeCheckerCore, strings: &lasso::Rodeo, val: Value) -> String {
+    print_type(core, strings, val.0, true)
+}
+
+/// Print the type accepted by the given use in PolySubML type syntax
+pub fn print_use_type(core: &TypeCheckerCore, strings: &lasso::Rodeo, u: Use) -> String {
+    print_type(core, strings, u.0, false)
+}
+
+/// Print the type of the given value for use in an interface. Unlike print_value_type, this never
+/// prints _ or ..., so the result is a type that the value can be checked against.
+pub fn print_interface_type(core: &TypeCheckerCore, strings: &lasso::Rodeo, val: Value) -> String {
+    let mut r = Reconstructor::new(core, strings);
+    r.interface = true;
+    let ty = r.visit(val.0, true);
+
+    let mut out = String::new();
+    print(&mut out, &ty, TOP);
+    out
+}
+
+fn children(ty: &Ty) -> Vec<&Ty> {
+    use Ty::*;
+    match ty {
+        Never | Any | Hole | Elided | Abstract(_) | Var(_) => Vec::new(),
+        Func(arg, ret) => vec![arg, ret],
+        Record(fields) => fields.iter().flat_map(|(_, r, w)| std::iter::once(r).chain(w)).collect(),
+        Case(cases) => cases.iter().map(|(_, ty)| ty).collect(),
+        Union(tys) | Intersect(tys) => tys.iter().collect(),
+        Poly(_, params, sub) => params
+            .iter()
+            .flat_map(|(_, upper, lower)| upper.iter().chain(lower))
+            .chain(std::iter::once(&**sub))
+            .collect(),
+        Rec(_, sub) => vec![sub],
+    }
+}
+
+// Names of type constructors, type parameters, and recursive types referenced by ty
+fn referenced_names<'t>(ty: &'t Ty, out: &mut Vec<&'t str>) {
+    if let Ty::Abstract(name) | Ty::Var(name) = ty {
+        out.push(name);
+    }
+    for child in children(ty) {
+        referenced_names(child, out);
+    }
+}
+
+fn contains_hole(ty: &Ty) -> bool {
+    *ty == Ty::Hole || children(ty).into_iter().any(contains_hole)
+}
+
+fn contains_elided(ty: &Ty) -> bool {
+    *ty == Ty::Elided || children(ty).into_iter().any(contains_elided)
+}
+
+fn rename_vars(ty: &mut Ty, f: &impl Fn(&str) -> Option<String>) {
+    use Ty::*;
+    match ty {
+        Never | Any | Hole | Elided | Abstract(_) => {}
+        Var(name) => {
+            if let Some(new) = f(name) {
+                *name = new;
+            }
+        }
+        Func(arg, ret) => {
+            rename_vars(arg, f);
+            rename_vars(ret, f);
+        }
+        Record(fields) => {
+            for (_, r, w) in fields {
+                rename_vars(r, f);
+                if let Some(w) = w {
+                    rename_vars(w, f);
+                }
+            }
+        }
+        Case(cases) => cases.iter_mut().for_each(|(_, ty)| rename_vars(ty, f)),
+        Union(tys) | Intersect(tys) => tys.iter_mut().for_each(|ty| rename_vars(ty, f)),
+        Poly(_, params, sub) => {
+            for (_, upper, lower) in params {
+                upper.iter_mut().chain(lower).for_each(|ty| rename_vars(ty, f));
+            }
+            rename_vars(sub, f);
+        }
+        Rec(_, sub) => rename_vars(sub, f),
+    }
+}
+
+fn to_type_expr(ty: &Ty, strings: &mut lasso::Rodeo, span: Span) -> ast::STypeExpr {
+    use ast::TypeExpr;
+    let sub = |ty: &Ty, strings: &mut lasso::Rodeo| to_type_expr(ty, strings, span);
+    let expr = match ty {
+        Ty::Never => TypeExpr::Bot,
+        Ty::Any => TypeExpr::Top,
+        Ty::Hole => TypeExpr::Hole,
+        // generalize_func rejects types that had to be elided
+        Ty::Elided => unreachable!(),
+        Ty::Abstract(name) | Ty::Var(name) => TypeExpr::Ident(strings.get_or_intern(name)),
+        Ty::Func(arg, ret) => TypeExpr::Func(Box::new(sub(arg, strings)), Box::new(sub(ret, strings))),
+        Ty::Record(fields) => TypeExpr::Record(
+            fields
+                .iter()
+                .map(|(name, r, w)| {
+                    let decl = match w {
+                        None => ast::FieldTypeDecl::Imm(sub(r, strings)),
+                        Some(w) if w == r => ast::FieldTypeDecl::RWSame(sub(r, strings)),
+                        Some(w) => ast::FieldTypeDecl::RWPair(sub(r, strings), sub(w, strings)),
+                    };
+                    ((strings.get_or_intern(name), span), decl)
+                })
+                .collect(),
+        ),
+        Ty::Case(cases) => TypeExpr::Case(
+            cases
+                .iter()
+                .map(|(tag, ty)| ((strings.get_or_intern(tag), span), Box::new(sub(ty, strings))))
+                .collect(),
+        ),
+        Ty::Union(tys) => TypeExpr::VarJoin(ast::JoinKind::Union, tys.iter().map(|ty| sub(ty, strings)).collect()),
+        Ty::Intersect(tys) => TypeExpr::VarJoin(ast::JoinKind::Intersect, tys.iter().map(|ty| sub(ty, strings)).collect()),
+        Ty::Poly(kind, params, ty) => {
+            let params = params.iter().map(|param| to_type_param(param, strings, span)).collect();
+            TypeExpr::Poly(params, Box::new(sub(ty, strings)), *kind)
+        }
+        Ty::Rec(name, ty) => TypeExpr::RecursiveDef(strings.get_or_intern(name), Box::new(sub(ty, strings))),
+    };
+    (expr, span)
+}
+
+fn to_type_param(
+    (name, upper, lower): &(String, Option<Ty>, Option<Ty>),
+    strings: &mut lasso::Rodeo,
+    span: Span,
+) -> ast::TypeParam {
+    let name = (strings.get_or_intern(name), span);
+    let upper = upper.as_ref().map(|ty| to_type_expr(ty, strings, span));
+    let lower = lower.as_ref().map(|ty| to_type_expr(ty, strings, span));
+    ast::TypeParam::new(name, None, upper, lower)
+}
+
+/// Type annotations that make an unannotated function definition polymorphic
+pub struct GeneralizedFunc {
+    pub type_params: Vec<ast::TypeParam>,
+    pub arg: ast::STypeExpr,
+    pub ret: ast::STypeExpr,
+    /// The resulting polymorphic function type, printed in PolySubML type syntax
+    pub printed: String,
+}
+
+/// Find a polymorphic type for a function definition whose inferred monomorphic type is func,
+/// where start is the number of type nodes before the function was checked. Inference variables
+/// created while checking the function that flow from the argument to the return type become
+/// type parameters, and any other constraints on them become bounds of the parameters.
+///
+/// Returns Ok(None) if there is nothing to generalize, and an explanation if the type can't
+/// be written as a type annotation. The annotations still have to be checked against the
+/// function definition, since this doesn't check whether the resulting type is valid.
+pub fn generalize_func(
+    core: &TypeCheckerCore,
+    strings: &mut lasso::Rodeo,
+    func: Value,
+    start: usize,
+    span: Span,
+) -> Result<Option<GeneralizedFunc>, &'static str> {
+    let Some(TypeNode::Value((VTypeHead::VFunc { arg, ret }, ..))) = core.r.get(func.0) else {
+        return Ok(None);
+    };
+    let (arg, ret) = (arg.0, ret.0);
+
+    // First find which local variables appear in the function type, and with which polarity
+    let mut r = Reconstructor::new(core, strings);
+    r.local_start = start;
+    let tys = [r.visit(arg, false), r.visit(ret, true)];
+    let mut taken: Vec<&str> = Vec::new();
+    tys.iter().for_each(|ty| referenced_names(ty, &mut taken));
+
+    // Variables which flow from a negative position to a positive position become type parameters,
+    // with all variables connected by such flows sharing the same parameter.
+    let local_vars = std::mem::take(&mut r.local_vars);
+    let mut groups: Vec<Vec<TypeNodeInd>> = Vec::new();
+    let mut group_of: HashMap<TypeNodeInd, usize> = HashMap::new();
+    for &(n, _) in local_vars.iter().filter(|v| !v.1) {
+        for &(p, _) in local_vars.iter().filter(|v| v.1) {
+            if n != p && core.r.get_edge(n, p).is_none() {
+                continue;
+            }
+            let g = match (group_of.get(&n).copied(), group_of.get(&p).copied()) {
+                (Some(g1), Some(g2)) if g1 != g2 => {
+                    let moved = std::mem::take(&mut groups[g2]);
+                    for &v in moved.iter() {
+                        group_of.insert(v, g1);
+                    }
+                    groups[g1].extend(moved);
+                    g1
+                }
+                (Some(g), _) | (None, Some(g)) => g,
+                (None, None) => {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                }
+            };
+            for v in [n, p] {
+                if group_of.insert(v, g).is_none() {
+                    groups[g].push(v);
+                }
+            }
+        }
+    }
+    groups.retain(|g| !g.is_empty());
+    if groups.is_empty() {
+        return Ok(None);
+    }
+    // Generalizing would give variables from outside the function an abstract type,
+    // which is sound but makes them unusable for anything else.
+    let is_outer_var = |j: TypeNodeInd| j.0 < start && matches!(core.r.get(j), Some(TypeNode::Var(_)));
+    if groups.iter().flatten().any(|&v| core.r.flows_to(v).any(is_outer_var)) {
+        return Err("its arguments flow into variables defined outside of the function");
+    }
+
+    // Give each parameter a placeholder name that can't clash with anything, and rename them
+    // in order of appearance afterwards so that the names don't depend on the order of type nodes.
+    let placeholder = |i: usize| format!("'{}", i);
+    let mut r = Reconstructor::new(core, r.strings);
+    r.local_start = start;
+    for (i, g) in groups.iter().enumerate() {
+        for &v in g {
+            r.params.insert(v, placeholder(i));
+        }
+    }
+    let arg_ty = r.visit(arg, false);
+    let ret_ty = r.visit(ret, true);
+
+    let mut params = Vec::new();
+    for (i, g) in groups.iter().enumerate() {
+        let upper = intersect(g.iter().map(|&v| r.var_bounds(v, false)).collect());
+        let lower = union(g.iter().map(|&v| r.var_bounds(v, true)).collect());
+        let upper = (upper != Ty::Any).then_some(upper);
+        let lower = (lower != Ty::Never).then_some(lower);
+        for bound in upper.iter().chain(&lower) {
+            let mut names = Vec::new();
+            referenced_names(bound, &mut names);
+            if contains_elided(bound) {
+                return Err("its type is too large to write out");
+            }
+            if names.iter().any(|name| name.starts_with('\'')) || contains_hole(bound) {
+                return Err("its type parameters would need bounds that depend on other type parameters");
+            }
+        }
+        params.push((placeholder(i), upper, lower));
+    }
+    if contains_elided(&arg_ty) || contains_elided(&ret_ty) {
+        return Err("its type is too large to write out");
+    }
+    if contains_hole(&arg_ty) || contains_hole(&ret_ty) {
+        return Err("its type depends on types which have not been inferred yet");
+    }
+
+    let mut order = Vec::new();
+    referenced_names(&arg_ty, &mut order);
+    referenced_names(&ret_ty, &mut order);
+    let mut new_names: HashMap<String, String> = HashMap::new();
+    let mut candidates = (b'a'..=b'z')
+        .map(|c| (c as char).to_string())
+        .chain((1..).map(|i| format!("a{}", i)))
+        .filter(|name| !taken.contains(&name.as_str()));
+    for name in order.into_iter().chain(params.iter().map(|p| p.0.as_str())) {
+        if name.starts_with('\'') && !new_names.contains_key(name) {
+            new_names.insert(name.to_owned(), candidates.next().unwrap());
+        }
+    }
+    let rename = |name: &str| new_names.get(name).cloned();
+
+    let mut ty = Ty::Poly(
+        PolyKind::Universal,
+        params,
+        Box::new(Ty::Func(Box::new(arg_ty), Box::new(ret_ty))),
+    );
+    rename_vars(&mut ty, &rename);
+    if let Ty::Poly(_, params, _) = &mut ty {
+        for param in params.iter_mut() {
+            param.0 = rename(&param.0).unwrap();
+        }
+        params.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    let mut printed = String::new();
+    print(&mut printed, &ty, TOP);
+
+    let Ty::Poly(_, params, sub) = ty else { unreachable!() };
+    let Ty::Func(arg_ty, ret_ty) = *sub else { unreachable!() };
+    Ok(Some(GeneralizedFunc {
+        type_params: params.iter().map(|param| to_type_param(param, strings, span)).collect(),
+        arg: to_type_expr(&arg_ty, strings, span),
+        ret: to_type_expr(&ret_ty, strings, span),
+        printed,
+    }))
+}
@@ -97,6 +97,31 @@ impl SpanManager {
         }
     }
 
+    pub fn num_sources(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns the source index and start and end byte offsets of the span
+    pub fn get(&self, span: Span) -> (usize, usize, usize) {
+        self.spans[span.0]
+    }
+
+    /// Short single line description of the span's position and text, e.g. for debug output
+    pub fn describe(&self, span: Span) -> String {
+        let (source_ind, l, r) = self.spans[span.0];
+        let source = &self.sources[source_ind];
+        let (y1, x1) = source.get_pos(l);
+        let (y2, x2) = source.get_pos(r);
+
+        let text = source.s[l..r].split_whitespace().collect::<Vec<_>>().join(" ");
+        let text = if text.chars().count() > 30 {
+            text.chars().take(27).collect::<String>() + "..."
+        } else {
+            text
+        };
+        format!("[{}] {}:{}-{}:{} `{}`", source_ind, y1 + 1, x1 + 1, y2 + 1, x2 + 1, text)
+    }
+
     fn new_span(&mut self, source_ind: usize, l: usize, r: usize) -> Span {
         let i = self.spans.len();
         self.spans.push((source_ind, l, r));
@@ -206,6 +231,30 @@ impl<'a> SpanMaker<'a> {
     }
 }
 
+/// A suggested change to a source: replace the bytes from start to end with the given text.
+/// Insertions have an empty range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub source: usize,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+impl TextEdit {
+    /// Apply edits for the same source. The edits must not overlap, but may be given in any order.
+    pub fn apply_all(source: &str, edits: &[TextEdit]) -> String {
+        let mut edits: Vec<_> = edits.iter().collect();
+        // Apply from the end so that earlier offsets remain valid. Edits at the same
+        // position are applied in reverse so that they end up in the given order.
+        edits.sort_by_key(|e| (e.start, e.end));
+        let mut out = source.to_owned();
+        for e in edits.into_iter().rev() {
+            out.replace_range(e.start..e.end, &e.text);
+        }
+        out
+    }
+}
+
 #[derive(Debug)]
 enum Item {
     Str(String),
@@ -252,6 +301,28 @@ impl SpannedError {
         new
     }
 
+    /// The insertions suggested by this error, as a list of fixes each consisting of one or more edits.
+    pub fn suggestions(&self, sm: &SpanManager) -> Vec<Vec<TextEdit>> {
+        let mut out = Vec::new();
+        for item in self.items.iter() {
+            if let &Item::Insert(ref before, span, ref after) = item {
+                let (source, l, r) = sm.get(span);
+                let edits = [(before, l), (after, r)]
+                    .into_iter()
+                    .filter(|(s, _)| !s.is_empty())
+                    .map(|(s, off)| TextEdit {
+                        source,
+                        start: off,
+                        end: off,
+                        text: s.clone(),
+                    })
+                    .collect();
+                out.push(edits);
+            }
+        }
+        out
+    }
+
     pub fn print(&self, sm: &SpanManager) -> String {
         let mut out = String::new();
         for item in self.items.iter() {
@@ -10,6 +10,7 @@ use std::u32;
 use crate::ast::InstantiateSourceKind;
 use crate::ast::StringId;
 use crate::core::*;
+use crate::reachability::TypeNodeInd;
 use crate::spans::Span;
 use crate::spans::SpannedError;
 
@@ -26,6 +27,14 @@ pub enum HoleSrc {
     BareVarPattern(Span), // Same as CheckedExpr but with higher priority
 }
 impl HoleSrc {
+    pub fn span(&self) -> Span {
+        use HoleSrc::*;
+        match *self {
+            Explicit(span) | OptAscribe(span) | CheckedExpr(span) | BareVarPattern(span) => span,
+            Instantiation((span, _), _) => span,
+        }
+    }
+
     fn priority(&self) -> usize {
         use HoleSrc::*;
         match self {
@@ -113,6 +122,113 @@ impl PartialTypeError {
     }
 }
 
+impl PartialTypeError {
+    /// Explain every step by which the value reached the use that rejected it, in order
+    /// starting from the expression where the value originated.
+    pub fn add_flow_explanation(&mut self, core: &TypeCheckerCore, strings: &lasso::Rodeo, pair: (Value, Use)) {
+        enum Item {
+            Explain((Value, Use)),
+            Hole(TypeNodeInd),
+            Check(Value, Use),
+        }
+
+        self.0
+            .push_str("Note: The value reaches the place where it is rejected through the following steps:");
+        let mut seen = HashSet::new();
+        let mut step = 0;
+        let mut stack = vec![Item::Explain(pair)];
+        while let Some(item) = stack.pop() {
+            match item {
+                Item::Explain(pair) => {
+                    if !seen.insert(pair) {
+                        continue;
+                    }
+                    match core.r.get_edge(pair.0.0, pair.1.0).unwrap().reason {
+                        FlowReason::Root(span) => {
+                            step += 1;
+                            self.0
+                                .push_str(format!("{}. A value flows into the type expected by this expression:", step));
+                            self.0.push_span(span);
+                        }
+                        // Push in reverse order so that the steps are printed in order of flow
+                        FlowReason::Transitivity(h) => {
+                            stack.push(Item::Explain((Value(h), pair.1)));
+                            stack.push(Item::Hole(h));
+                            stack.push(Item::Explain((pair.0, Use(h))));
+                        }
+                        FlowReason::Check(v, u) => {
+                            stack.push(Item::Check(v, u));
+                            stack.push(Item::Explain((v, u)));
+                        }
+                    }
+                }
+                Item::Hole(h) => {
+                    if let Some(&TypeNode::Var(data)) = core.r.get(h) {
+                        step += 1;
+                        self.0
+                            .push_str(format!("{}. It passes through the inferred type here:", step));
+                        self.0.push_span(data.src.span());
+                    }
+                }
+                Item::Check(v, u) => {
+                    if let (Some(TypeNode::Value(lhs)), Some(TypeNode::Use(rhs))) = (core.r.get(v.0), core.r.get(u.0)) {
+                        step += 1;
+                        let lhs_desc = describe_value_head(strings, &core.type_ctors, &lhs.0);
+                        let rhs_desc = describe_use_head(strings, &core.type_ctors, &rhs.0);
+                        self.0.push_str(format!("{}. Where the {} from here:", step, lhs_desc));
+                        self.0.push_span(lhs.1);
+                        self.0.push_str(format!(
+                            "   is checked against the {} here, so part of it flows onward:",
+                            rhs_desc
+                        ));
+                        self.0.push_span(rhs.1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn type_ctor_name(strings: &lasso::Rodeo, type_ctors: &[TypeCtor], ty: TypeCtorInd) -> String {
+    strings.resolve(&type_ctors[ty.0].name).to_owned()
+}
+
+pub fn describe_value_head(strings: &lasso::Rodeo, type_ctors: &[TypeCtor], head: &VTypeHead) -> String {
+    use VTypeHead::*;
+    match head {
+        VUnion(_) => "union".to_owned(),
+        VInstantiateExist { .. } => "instantiated existential type".to_owned(),
+        VTop => "value of type any".to_owned(),
+        VFunc { .. } => "function".to_owned(),
+        VObj { .. } => "record".to_owned(),
+        VCase { case: (tag, _) } => format!("variant `{}", strings.resolve(tag)),
+        VAbstract { ty } => format!("value of type {}", type_ctor_name(strings, type_ctors, *ty)),
+        VPolyHead(..) => "polymorphic value".to_owned(),
+        VTypeVar(tv) => format!("value of type parameter {}", strings.resolve(&tv.name)),
+        VDisjointIntersect(..) => "intersection".to_owned(),
+    }
+}
+
+pub fn describe_use_head(strings: &lasso::Rodeo, type_ctors: &[TypeCtor], head: &UTypeHead) -> String {
+    use UTypeHead::*;
+    match head {
+        UIntersection(_) => "intersection".to_owned(),
+        UInstantiateUni { .. } => "instantiation of a polymorphic type".to_owned(),
+        UBot => "type never".to_owned(),
+        UFunc { .. } => "function type".to_owned(),
+        UObj { .. } => "record type".to_owned(),
+        UCase { .. } => "match".to_owned(),
+        UAbstract { ty } => format!("type {}", type_ctor_name(strings, type_ctors, *ty)),
+        UOverload { cases } => {
+            let names: Vec<_> = cases.iter().map(|&(ty, _)| type_ctor_name(strings, type_ctors, ty)).collect();
+            format!("type {}", names.join(" or "))
+        }
+        UPolyHead(..) => "polymorphic type".to_owned(),
+        UTypeVar(tv) => format!("type parameter {}", strings.resolve(&tv.name)),
+        UDisjointUnion(..) => "union".to_owned(),
+    }
+}
+
 enum TMsg {
     BeA(String),
     HaveTy(String, Option<Span>),
@@ -177,6 +293,10 @@ pub fn type_mismatch_err(
             let name = strings.resolve(&tycon.name);
             HaveTy(name.to_owned(), tycon.span)
         }
+        UOverload { ref cases } => {
+            let names: Vec<_> = cases.iter().map(|&(ty, _)| strings.resolve(&type_ctors[ty.0].name)).collect();
+            HaveTy(names.join(" or "), None)
+        }
         // VAbstract { ty, .. } => &type_ctors[ty.0].debug,
         UTypeVar(tv) => BeA(format!("type parameter {}", strings.resolve(&tv.name))),
         UDisjointUnion(..) => be_a("union"),
@@ -233,6 +353,19 @@ pub fn missing_field_err(lhs_span: Span, rhs_span: Span, name: &str) -> PartialT
     parts
 }
 
+pub fn missing_bound_err(name: &str, kind: &str, bounded_span: Span, unbounded_span: Span) -> PartialTypeError {
+    let mut parts = PartialTypeError::new();
+    parts.push(
+        format!("TypeError: Type parameter {} is required to have {} bound here:", name, kind),
+        bounded_span,
+    );
+    parts.push(
+        "But the type parameter is declared without a matching bound here:".to_string(),
+        unbounded_span,
+    );
+    parts
+}
+
 pub fn immutable_field_err(lhs_span: Span, rhs_span: Span, name: &str) -> PartialTypeError {
     let mut parts = PartialTypeError::new();
     parts.push(
@@ -308,3 +441,13 @@ fn backtrack_hole_list_sub(
         }
     }
 }
+
+pub fn limit_exceeded_err(what: &str, limit: usize, span: Span) -> SpannedError {
+    SpannedError::new1(
+        format!(
+            "TypeError: Type checking exceeded the limit of {} {} while checking this expression:",
+            limit, what
+        ),
+        span,
+    )
+}
@@ -1,22 +1,98 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use crate::ast;
 use crate::ast::StringId;
 use crate::core::*;
+use crate::graph_dump::ConstraintGraph;
 use crate::parse_types::TreeMaterializerState;
 use crate::parse_types::TypeParser;
+use crate::reachability::TypeNodeInd;
+use crate::reconstruct;
+use crate::spans::Span;
+use crate::spans::Spanned;
 use crate::spans::SpannedError as SyntaxError;
 use crate::type_errors::HoleSrc;
 use crate::unwindmap::UnwindMap;
 use crate::unwindmap::UnwindPoint;
+use crate::warnings::Warning;
+use crate::warnings::WarningKind;
 
 use UTypeHead::*;
 use VTypeHead::*;
 
 type Result<T> = std::result::Result<T, SyntaxError>;
 
+/// The type node recorded for an expression. Expressions which are inferred have a Value,
+/// while expressions which are checked against an expected type have a Use.
+#[derive(Debug, Clone, Copy)]
+pub enum ExprType {
+    Value(Value),
+    Use(Use),
+}
+
+fn pattern_vars(pat: &ast::LetPattern, out: &mut Vec<StringId>) {
+    use ast::LetPattern::*;
+    match pat {
+        Alias(pat, (name, _), _) => {
+            pattern_vars(pat, out);
+            out.push(*name);
+        }
+        Case(_, pat) => pattern_vars(pat, out),
+        Record(((_, fields), _)) => {
+            for (_, pat) in fields {
+                pattern_vars(pat, out);
+            }
+        }
+        Var((name, _), _) => out.extend(*name),
+    }
+}
+
+fn pattern_is_annotated(pat: &ast::LetPattern) -> bool {
+    use ast::LetPattern::*;
+    match pat {
+        Alias(pat, _, ty) => ty.is_some() || pattern_is_annotated(pat),
+        Case(_, pat) => pattern_is_annotated(pat),
+        Record(((params, fields), _)) => !params.is_empty() || fields.iter().any(|(_, pat)| pattern_is_annotated(pat)),
+        Var(_, ty) => ty.is_some(),
+    }
+}
+
+/// Annotate each variable in an unannotated pattern with its part of ty, the type of the whole pattern.
+/// Returns None if the type doesn't have the same shape as the pattern.
+fn annotate_pattern(pat: &ast::LetPattern, ty: ast::STypeExpr) -> Option<ast::LetPattern> {
+    use ast::LetPattern::*;
+    Some(match (pat, ty.0) {
+        (&Var(var, None), ty_expr) => Var(var, Some((ty_expr, ty.1))),
+        (Case(tag, sub), ast::TypeExpr::Case(mut cases)) => {
+            let i = cases.iter().position(|case| case.0.0 == tag.0)?;
+            Case(*tag, Box::new(annotate_pattern(sub, *cases.swap_remove(i).1)?))
+        }
+        (Record(((params, fields), span)), ast::TypeExpr::Record(mut decls)) if params.is_empty() => {
+            let mut annotated = Vec::with_capacity(fields.len());
+            for (name, sub) in fields {
+                let i = decls.iter().position(|decl| decl.0.0 == name.0)?;
+                let (ast::FieldTypeDecl::Imm(field_ty)
+                | ast::FieldTypeDecl::RWSame(field_ty)
+                | ast::FieldTypeDecl::RWPair(field_ty, _)) = decls.swap_remove(i).1;
+                annotated.push((*name, Box::new(annotate_pattern(sub, field_ty)?)));
+            }
+            Record(((Vec::new(), annotated), *span))
+        }
+        _ => return None,
+    })
+}
+
+// The arms of a match expression, for detecting arms that can never be taken
+struct MatchArms {
+    bound: Use,
+    arg_span: Span,
+    cases: Vec<Spanned<StringId>>,
+    wildcard: Option<Span>,
+}
+
 type BindingsUnwindPoint = (UnwindPoint, UnwindPoint, ScopeLvl);
 pub struct Bindings {
     pub vars: UnwindMap<StringId, Value>,
@@ -57,6 +133,40 @@ pub struct TypeckState {
     TY_FLOAT: TypeCtorInd,
     TY_INT: TypeCtorInd,
     TY_STR: TypeCtorInd,
+    TY_I32: TypeCtorInd,
+    TY_I64: TypeCtorInd,
+    TY_U8: TypeCtorInd,
+    // Marker type of the builtin compare function, which is given a fresh overloaded type at each use
+    TY_COMPARE: Option<TypeCtorInd>,
+
+    overloaded_arith: bool,
+
+    // Type nodes for each expression in the current script, for use by editor tooling
+    expr_types: Vec<(Span, ExprType)>,
+
+    // Errors found so far in the current script. After an error, the offending expression
+    // is given the never type and checking continues with the rest of the script.
+    errors: Vec<SyntaxError>,
+
+    // Match expressions in the current script
+    matches: Vec<MatchArms>,
+    // Arguments of print statements in the current script
+    prints: Vec<(Span, Value)>,
+
+    // Whether to save the part of the type graph created by each script, for debugging
+    record_graph: bool,
+    last_graph: Option<ConstraintGraph>,
+    // Number of type nodes before the current script, i.e. where its part of the graph starts
+    graph_start: usize,
+
+    // Whether to save the inference variables for explicit _ annotations in each script
+    record_holes: bool,
+    last_holes: Vec<(Span, TypeNodeInd)>,
+
+    // Whether to infer polymorphic types for unannotated function definitions,
+    // and the warnings for functions in the current script that couldn't be generalized
+    generalize: bool,
+    generalize_warnings: Vec<Warning>,
 }
 impl TypeckState {
     #[allow(non_snake_case)]
@@ -66,6 +176,9 @@ impl TypeckState {
         let TY_FLOAT = core.add_builtin_type(strings.get_or_intern_static("float"));
         let TY_INT = core.add_builtin_type(strings.get_or_intern_static("int"));
         let TY_STR = core.add_builtin_type(strings.get_or_intern_static("str"));
+        let TY_I32 = core.add_builtin_type(strings.get_or_intern_static("i32"));
+        let TY_I64 = core.add_builtin_type(strings.get_or_intern_static("i64"));
+        let TY_U8 = core.add_builtin_type(strings.get_or_intern_static("u8"));
 
         let mut new = Self {
             core,
@@ -75,6 +188,29 @@ impl TypeckState {
             TY_FLOAT,
             TY_INT,
             TY_STR,
+            TY_I32,
+            TY_I64,
+            TY_U8,
+            TY_COMPARE: None,
+
+            overloaded_arith: false,
+
+            expr_types: Vec::new(),
+
+            errors: Vec::new(),
+
+            matches: Vec::new(),
+            prints: Vec::new(),
+
+            record_graph: false,
+            last_graph: None,
+            graph_start: 0,
+
+            record_holes: false,
+            last_holes: Vec::new(),
+
+            generalize: false,
+            generalize_warnings: Vec::new(),
         };
 
         let n = new.bindings.unwind_point();
@@ -86,6 +222,114 @@ impl TypeckState {
         new
     }
 
+    /// When enabled, the int arithmetic and comparison operators (except / and %) also accept floats.
+    pub fn set_overloaded_arith(&mut self, enabled: bool) {
+        self.overloaded_arith = enabled;
+    }
+    pub fn overloaded_arith(&self) -> bool {
+        self.overloaded_arith
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.core.limits = limits;
+    }
+    pub fn limits(&self) -> Limits {
+        self.core.limits
+    }
+
+    pub fn set_verbose_errors(&mut self, enabled: bool) {
+        self.core.verbose_errors = enabled;
+    }
+    pub fn verbose_errors(&self) -> bool {
+        self.core.verbose_errors
+    }
+
+    pub fn set_record_graph(&mut self, enabled: bool) {
+        self.record_graph = enabled;
+        if !enabled {
+            self.last_graph = None;
+        }
+    }
+    pub fn record_graph(&self) -> bool {
+        self.record_graph
+    }
+    /// The type graph created by the last checked script, if recording is enabled
+    pub fn last_graph(&self) -> Option<&ConstraintGraph> {
+        self.last_graph.as_ref()
+    }
+    pub fn set_record_holes(&mut self, enabled: bool) {
+        self.record_holes = enabled;
+    }
+    pub fn record_holes(&self) -> bool {
+        self.record_holes
+    }
+
+    /// When enabled, unannotated function definitions assigned with let are given
+    /// a polymorphic type if possible.
+    pub fn set_generalize(&mut self, enabled: bool) {
+        self.generalize = enabled;
+    }
+    pub fn generalize(&self) -> bool {
+        self.generalize
+    }
+
+    pub fn clear_last_graph(&mut self) {
+        self.last_graph = None;
+    }
+
+    /// Add a variable with the given type signature that is visible to all scripts.
+    pub fn add_builtin_var(&mut self, name: StringId, tyexpr: &ast::STypeExpr) -> Result<()> {
+        let n = self.bindings.unwind_point();
+        let (v, _) = self.parse_type_signature(tyexpr)?;
+        self.bindings.vars.insert(name, v);
+        self.bindings.make_permanent(n);
+        Ok(())
+    }
+
+    /// Add a builtin that compares two values of the same primitive type, e.g. `compare (1, 2)`.
+    /// Since this can't be written as a type signature, each use gets a fresh overloaded type.
+    pub fn add_builtin_compare(&mut self, name: StringId, span: Span) {
+        let ty = self.core.add_builtin_type(name);
+        self.TY_COMPARE = Some(ty);
+
+        let n = self.bindings.unwind_point();
+        let v = self.core.simple_val(ty, span);
+        self.bindings.vars.insert(name, v);
+        self.bindings.make_permanent(n);
+    }
+
+    fn is_compare_marker(&self, v: Value) -> bool {
+        matches!(self.core.r.get(v.0), Some(TypeNode::Value((VAbstract { ty }, ..))) if Some(*ty) == self.TY_COMPARE)
+    }
+
+    /// The type of a use of the builtin compare function. The type of the first argument determines
+    /// which case applies, and the second argument must have the same type.
+    fn compare_type(&mut self, strings: &mut lasso::Rodeo, span: Span) -> Value {
+        let (rhs_val, rhs_use) = self.core.var(HoleSrc::CheckedExpr(span), self.bindings.scopelvl);
+
+        let mut cases = Vec::new();
+        for ty in [
+            self.TY_BOOL,
+            self.TY_FLOAT,
+            self.TY_INT,
+            self.TY_STR,
+            self.TY_I32,
+            self.TY_I64,
+            self.TY_U8,
+        ] {
+            cases.push((ty, vec![(rhs_val, self.core.simple_use(ty, span))]));
+        }
+        let lhs_use = self.core.new_use(UOverload { cases }, span, None);
+
+        let fields = vec![
+            (strings.get_or_intern_static("_0"), (lhs_use, None, span)),
+            (strings.get_or_intern_static("_1"), (rhs_use, None, span)),
+        ];
+        let arg = self.core.obj_use(fields, span);
+        let ret = self.core.simple_val(self.TY_INT, span);
+        self.core.new_val(VTypeHead::VFunc { arg, ret }, span, None)
+    }
+
     fn parse_type_signature(&mut self, tyexpr: &ast::STypeExpr) -> Result<(Value, Use)> {
         let temp = TypeParser::new(&self.bindings.types).parse_type(tyexpr)?;
         let mut mat = TreeMaterializerState::new(self.bindings.scopelvl);
@@ -98,7 +342,30 @@ impl TypeckState {
         Ok(mat.with(&mut self.core).add_pattern(temp, &mut self.bindings))
     }
 
+    /// Save the graph as of the first error in the script, before it is rolled back.
+    fn record_error_graph(&mut self, strings: &lasso::Rodeo) {
+        if self.record_graph && self.last_graph.is_none() {
+            self.last_graph = Some(self.core.constraint_graph(strings, self.graph_start));
+        }
+    }
+
     fn check_expr(&mut self, strings: &mut lasso::Rodeo, expr: &ast::SExpr, bound: Use) -> Result<()> {
+        self.expr_types.push((expr.1, ExprType::Use(bound)));
+        let expr_types_len = self.expr_types.len();
+        self.check_expr_sub(strings, expr, bound)?;
+
+        // When the expected type is just an inference variable (e.g. the result of a call), the
+        // values flowing into the variable are more informative than the type it is used as.
+        let inferred = self.expr_types[expr_types_len..]
+            .iter()
+            .any(|&(span, ty)| span == expr.1 && matches!(ty, ExprType::Value(..)));
+        if !inferred && matches!(self.core.r.get(bound.0), Some(TypeNode::Var(_))) {
+            self.expr_types.push((expr.1, ExprType::Value(Value(bound.0))));
+        }
+        Ok(())
+    }
+
+    fn check_expr_sub(&mut self, strings: &mut lasso::Rodeo, expr: &ast::SExpr, bound: Use) -> Result<()> {
         use ast::Expr::*;
         match &expr.0 {
             Block(e) => {
@@ -106,7 +373,7 @@ impl TypeckState {
                 let mark = self.bindings.unwind_point();
 
                 for stmt in e.statements.iter() {
-                    self.check_statement(strings, stmt, false)?;
+                    self.check_statement(strings, stmt)?;
                 }
 
                 self.check_expr(strings, &e.expr, bound)?;
@@ -182,7 +449,7 @@ impl TypeckState {
 
                 for ((pattern, pattern_span), rhs_expr) in cases {
                     use ast::LetPattern::*;
-                    match pattern {
+                    match pattern.without_aliases() {
                         Case(tag, val_pat) => {
                             if let Some(old_span) = case_names.insert(&tag.0, *pattern_span) {
                                 return Err(SyntaxError::new2(
@@ -194,7 +461,25 @@ impl TypeckState {
                             }
 
                             let mark = self.bindings.unwind_point();
-                            let pattern_bound = self.process_let_pattern(&*val_pat, true)?;
+                            let pattern_bound = if let Alias(..) = pattern {
+                                // Aliases are bound to the whole variant, so match the full pattern
+                                // against a value with this tag and the payload as its contents.
+                                let scopelvl = self.bindings.scopelvl;
+                                let (payload_val, payload_use) =
+                                    self.core.var(HoleSrc::CheckedExpr(*pattern_span), scopelvl);
+                                let case_val = self.core.new_val(
+                                    VCase {
+                                        case: (tag.0, payload_val),
+                                    },
+                                    tag.1,
+                                    None,
+                                );
+                                let pattern_bound = self.process_let_pattern(pattern, true)?;
+                                self.core.flow(strings, case_val, pattern_bound, *pattern_span, scopelvl)?;
+                                payload_use
+                            } else {
+                                self.process_let_pattern(&*val_pat, true)?
+                            };
                             // Note: bound is bound for the result types, not the pattern
                             self.check_expr(strings, rhs_expr, bound)?;
                             case_type_pairs.push((tag.0, pattern_bound));
@@ -206,9 +491,8 @@ impl TypeckState {
                                 *pattern_span,
                             ));
                         }
-                        // Wildcard case - only Var patterns will actually work here.
-                        // Any other pattern will result in a type error.
-                        Var(..) => {
+                        // Wildcard case - a Var pattern, possibly with aliases
+                        Alias(..) | Var(..) => {
                             if let Some(old_span) = wildcard {
                                 return Err(SyntaxError::new2(
                                     "SyntaxError: Duplicate match pattern",
@@ -231,6 +515,18 @@ impl TypeckState {
                 }
 
                 let bound = self.core.case_use(case_type_pairs, wildcard_type, arg_span);
+                self.matches.push(MatchArms {
+                    bound,
+                    arg_span,
+                    cases: cases
+                        .iter()
+                        .filter_map(|((pattern, span), _)| match pattern.without_aliases() {
+                            ast::LetPattern::Case((tag, _), _) => Some((*tag, *span)),
+                            _ => None,
+                        })
+                        .collect(),
+                    wildcard,
+                });
                 self.check_expr(strings, match_expr, bound)?;
             }
 
@@ -246,12 +542,22 @@ impl TypeckState {
     }
 
     fn infer_expr(&mut self, strings: &mut lasso::Rodeo, expr: &ast::SExpr) -> Result<Value> {
+        let val = self.infer_expr_sub(strings, expr)?;
+        self.expr_types.push((expr.1, ExprType::Value(val)));
+        Ok(val)
+    }
+
+    fn infer_expr_sub(&mut self, strings: &mut lasso::Rodeo, expr: &ast::SExpr) -> Result<Value> {
         use ast::Expr::*;
 
         match &expr.0 {
             BinOp(e) => {
                 use ast::Literal::*;
                 let (arg_class, ret_class) = &e.op_type;
+                if self.overloaded_arith && matches!(arg_class, Some(Int)) && !matches!(e.op, ast::Op::Div | ast::Op::Rem) {
+                    return self.infer_overloaded_binop(strings, e, expr.1);
+                }
+
                 let (lhs_bound, rhs_bound) = match arg_class {
                     Some(arg_class) => {
                         let cls = match arg_class {
@@ -259,6 +565,9 @@ impl TypeckState {
                             Float => self.TY_FLOAT,
                             Int => self.TY_INT,
                             Str => self.TY_STR,
+                            I32 => self.TY_I32,
+                            I64 => self.TY_I64,
+                            U8 => self.TY_U8,
                         };
 
                         (self.core.simple_use(cls, e.lhs.1), self.core.simple_use(cls, e.rhs.1))
@@ -273,6 +582,9 @@ impl TypeckState {
                     Float => self.TY_FLOAT,
                     Int => self.TY_INT,
                     Str => self.TY_STR,
+                    I32 => self.TY_I32,
+                    I64 => self.TY_I64,
+                    U8 => self.TY_U8,
                 };
                 Ok(self.core.simple_val(cls, expr.1))
             }
@@ -283,7 +595,7 @@ impl TypeckState {
                 let mark = self.bindings.unwind_point();
 
                 for stmt in e.statements.iter() {
-                    self.check_statement(strings, stmt, false)?;
+                    self.check_statement(strings, stmt)?;
                 }
 
                 let res = self.infer_expr(strings, &e.expr)?;
@@ -363,6 +675,9 @@ impl TypeckState {
                     Float => self.TY_FLOAT,
                     Int => self.TY_INT,
                     Str => self.TY_STR,
+                    I32 => self.TY_I32,
+                    I64 => self.TY_I64,
+                    U8 => self.TY_U8,
                 };
                 Ok(self.core.simple_val(ty, span))
             }
@@ -410,10 +725,16 @@ impl TypeckState {
                 Ok(sig_type.0)
             }
             Variable(e) => {
-                if let Some(v) = self.bindings.vars.get(&e.name) {
-                    Ok(*v)
+                if let Some(&v) = self.bindings.vars.get(&e.name) {
+                    if self.is_compare_marker(v) {
+                        return Ok(self.compare_type(strings, expr.1));
+                    }
+                    Ok(v)
                 } else {
-                    Err(SyntaxError::new1(format!("SyntaxError: Undefined variable"), expr.1))
+                    // Nothing was added to the type graph yet, so the rest of the statement can still be
+                    // checked by giving the variable the never type, which flows anywhere without errors.
+                    self.errors.push(SyntaxError::new1("SyntaxError: Undefined variable", expr.1));
+                    Ok(self.core.bot())
                 }
             }
 
@@ -426,9 +747,92 @@ impl TypeckState {
         }
     }
 
+    /// Check an unannotated function definition, then if its inferred type can be generalized,
+    /// roll that back and check it again with the inferred type parameters and annotations added.
+    fn infer_generalized_func(
+        &mut self,
+        strings: &mut lasso::Rodeo,
+        (name, name_span): Spanned<StringId>,
+        expr: &ast::SExpr,
+        e: &ast::expr::FuncDefExpr,
+    ) -> Result<Value> {
+        let start = self.core.num_type_nodes();
+        let lens = (
+            self.errors.len(),
+            self.expr_types.len(),
+            self.matches.len(),
+            self.generalize_warnings.len(),
+            self.prints.len(),
+        );
+        let rollback = |s: &mut Self| {
+            s.core.revert();
+            s.errors.truncate(lens.0);
+            s.expr_types.truncate(lens.1);
+            s.matches.truncate(lens.2);
+            s.generalize_warnings.truncate(lens.3);
+            s.prints.truncate(lens.4);
+        };
+
+        self.core.save();
+        let mono = self.infer_expr(strings, expr);
+        let sig = match mono {
+            Ok(mono) if self.errors.len() == lens.0 => {
+                reconstruct::generalize_func(&self.core, strings, mono, start, expr.1).map_err(String::from)
+            }
+            _ => Ok(None),
+        };
+        let sig = match sig {
+            Ok(Some(sig)) => sig,
+            Ok(None) => {
+                self.core.make_permanent();
+                return mono;
+            }
+            Err(reason) => {
+                self.core.make_permanent();
+                self.not_generalized(strings, name, name_span, &reason);
+                return mono;
+            }
+        };
+        rollback(self);
+
+        let param = annotate_pattern(&e.param.0, sig.arg.clone()).unwrap_or_else(|| {
+            // The alias name isn't a valid identifier, so it can't be referenced by the function body
+            let alias = (strings.get_or_intern_static("<arg>"), e.param.1);
+            ast::LetPattern::Alias(Box::new(e.param.0.clone()), alias, Some(sig.arg))
+        });
+        let poly_expr = ast::expr::func_def(Some(sig.type_params), (param, e.param.1), Some(sig.ret), e.body.clone());
+
+        self.core.save();
+        let poly = self.infer_expr(strings, &(poly_expr, expr.1));
+        if poly.is_ok() && self.errors.len() == lens.0 {
+            self.core.make_permanent();
+            return poly;
+        }
+
+        // The annotations were rejected, e.g. because they need a union or intersection of
+        // type parameters, so fall back to the monomorphic type.
+        rollback(self);
+        let reason = format!("its inferred type `{}` does not type check", sig.printed);
+        self.not_generalized(strings, name, name_span, &reason);
+        self.infer_expr(strings, expr)
+    }
+
+    fn not_generalized(&mut self, strings: &lasso::Rodeo, name: StringId, span: Span, reason: &str) {
+        let msg = SyntaxError::new1(
+            format!(
+                "Warning [not-generalized]: Function {} was not generalized since {}, so it can only be used at a single type",
+                strings.resolve(&name),
+                reason
+            ),
+            span,
+        );
+        self.generalize_warnings
+            .push(Warning::new(WarningKind::NotGeneralized, span, msg));
+    }
+
     fn check_let_def(&mut self, strings: &mut lasso::Rodeo, lhs: &ast::LetPattern, expr: &ast::SExpr) -> Result<()> {
         // Check if left hand side is a simple assignment with no type annotation
-        if let &ast::LetPattern::Var((Some(name), _), None) = lhs {
+        if let &ast::LetPattern::Var((Some(name), name_span), None) = lhs {
             // If lefthand side is a simple assignment, avoid adding an inference var
             // (and hence the possibility of prompting the user to add a type annotation)
             // when the type is "obvious" or redundant from the right hand side.
@@ -436,6 +840,16 @@ impl TypeckState {
             // so don't prompt for redundant annotations on the assignment.
             use ast::Expr::*;
             match &expr.0 {
+                FuncDef(e)
+                    if self.generalize
+                        && e.type_params.is_none()
+                        && e.return_type.is_none()
+                        && !pattern_is_annotated(&e.param.0) =>
+                {
+                    let ty = self.infer_generalized_func(strings, (name, name_span), expr, e)?;
+                    self.bindings.vars.insert(name, ty);
+                    return Ok(());
+                }
                 FuncDef(..) | Literal(..) | Typed(..) | Variable(..) => {
                     let ty = self.infer_expr(strings, expr)?;
                     self.bindings.vars.insert(name, ty);
@@ -504,32 +918,75 @@ impl TypeckState {
         Ok(())
     }
 
-    fn check_statement(
+    /// Operators like + are typed as the intersection of int * int -> int and float * float -> float.
+    /// Which case applies is determined by the type of the left hand side.
+    fn infer_overloaded_binop(
         &mut self,
         strings: &mut lasso::Rodeo,
-        def: &ast::Statement,
-        allow_useless_exprs: bool,
-    ) -> Result<()> {
+        e: &ast::expr::BinOpExpr,
+        span: crate::spans::Span,
+    ) -> Result<Value> {
+        let (rhs_val, rhs_use) = self.core.var(HoleSrc::CheckedExpr(e.rhs.1), self.bindings.scopelvl);
+        let (res_val, res_use) = self.core.var(HoleSrc::CheckedExpr(span), self.bindings.scopelvl);
+
+        let mut cases = Vec::new();
+        for ty in [self.TY_INT, self.TY_FLOAT] {
+            let ret_ty = if matches!(e.op_type.1, ast::Literal::Bool) {
+                self.TY_BOOL
+            } else {
+                ty
+            };
+            let flows = vec![
+                (rhs_val, self.core.simple_use(ty, e.rhs.1)),
+                (self.core.simple_val(ret_ty, span), res_use),
+            ];
+            cases.push((ty, flows));
+        }
+
+        let lhs_bound = self.core.new_use(UOverload { cases }, e.lhs.1, None);
+        self.check_expr(strings, &e.lhs, lhs_bound)?;
+        self.check_expr(strings, &e.rhs, rhs_use)?;
+        Ok(res_val)
+    }
+
+    /// Checking stops at the first error in a statement, since a failed flow may have added only
+    /// some of its constraints. Everything the statement added is then rolled back so that the
+    /// rest of the enclosing block or script can still be checked on a consistent graph.
+    fn check_statement(&mut self, strings: &mut lasso::Rodeo, def: &ast::Statement) -> Result<()> {
+        let mark = self.bindings.unwind_point();
+        self.core.save();
+        match self.check_statement_sub(strings, def) {
+            Ok(()) => self.core.make_permanent(),
+            Err(e) => {
+                self.errors.push(e);
+                self.record_error_graph(strings);
+                self.core.revert();
+                self.bindings.unwind(mark);
+                self.bind_never(def);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind any variables the statement would have defined to the never type
+    /// so that later uses of them don't report spurious undefined variable errors.
+    fn bind_never(&mut self, def: &ast::Statement) {
+        let mut names = Vec::new();
+        match def {
+            ast::Statement::LetDef((pattern, _)) => pattern_vars(pattern, &mut names),
+            ast::Statement::LetRecDef(defs) => names.extend(defs.iter().map(|&(name, _)| name)),
+            _ => {}
+        }
+        for name in names {
+            self.bindings.vars.insert(name, self.core.bot());
+        }
+    }
+
+    fn check_statement_sub(&mut self, strings: &mut lasso::Rodeo, def: &ast::Statement) -> Result<()> {
         use ast::Statement::*;
         match def {
             Empty => {}
             Expr(expr) => {
-                if !allow_useless_exprs {
-                    use ast::Expr::*;
-                    match &expr.0 {
-                        BinOp(_) | Case(_) | FieldAccess(_) | FuncDef(_) | InstantiateExist(_) | InstantiateUni(_)
-                        | Literal(_) | Record(_) | Variable(_) => {
-                            return Err(SyntaxError::new1(
-                                format!(
-                                    "SyntaxError: Only block, call, field set, if, loop, match, and typed expressions can appear in a sequence. The value of this expression will be ignored, which is likely unintentional. If you did intend to ignore the value of this expression, do so explicitly via let _ = ..."
-                                ),
-                                expr.1,
-                            ));
-                        }
-                        _ => {}
-                    };
-                }
-
                 self.check_expr(strings, expr, self.core.top_use())?;
             }
             LetDef((pattern, var_expr)) => {
@@ -540,38 +997,304 @@ impl TypeckState {
             }
             Println(exprs) => {
                 for expr in exprs {
-                    self.check_expr(strings, expr, self.core.top_use())?;
+                    let val = self.infer_expr(strings, expr)?;
+                    self.prints.push((expr.1, val));
                 }
             }
         };
         Ok(())
     }
 
-    pub fn check_script(&mut self, strings: &mut lasso::Rodeo, parsed: &[ast::Statement]) -> Result<()> {
+    /// Check whether type a is a subtype of type b. Any changes to the type graph are rolled back.
+    /// Returns Err if either type is invalid, and Ok(Err(explanation)) if a is not a subtype of b.
+    pub fn is_subtype(&mut self, strings: &mut lasso::Rodeo, a: &ast::STypeExpr, b: &ast::STypeExpr) -> Result<Result<()>> {
+        let a_parsed = TypeParser::new(&self.bindings.types).parse_type(a)?;
+        let b_parsed = TypeParser::new(&self.bindings.types).parse_type(b)?;
+
+        self.core.save();
+        let mut mat = TreeMaterializerState::new(self.bindings.scopelvl);
+        let (a_val, _) = mat.with(&mut self.core).add_type(a_parsed);
+        let (_, b_use) = mat.with(&mut self.core).add_type(b_parsed);
+        let res = self.core.flow(strings, a_val, b_use, a.1, self.bindings.scopelvl);
+        self.core.revert();
+        Ok(res)
+    }
+
+    /// Check that each variable declared by an interface is defined with a subtype of the declared type.
+    /// This does not change the types of any variables.
+    pub fn check_interface(
+        &mut self,
+        strings: &mut lasso::Rodeo,
+        decls: &[ast::InterfaceDecl],
+    ) -> std::result::Result<(), Vec<SyntaxError>> {
+        let mut errors = Vec::new();
+        self.core.save();
+        for ((name, name_span), ty) in decls {
+            let Some(&val) = self.bindings.vars.get(name) else {
+                errors.push(SyntaxError::new1(
+                    format!(
+                        "InterfaceError: Variable {} is declared in the interface but is not defined",
+                        strings.resolve(name)
+                    ),
+                    *name_span,
+                ));
+                continue;
+            };
+
+            let res = TypeParser::new(&self.bindings.types).parse_type(ty).and_then(|parsed| {
+                let mut mat = TreeMaterializerState::new(self.bindings.scopelvl);
+                let (_, bound) = mat.with(&mut self.core).add_type(parsed);
+                self.core.flow(strings, val, bound, ty.1, self.bindings.scopelvl)
+            });
+            if let Err(mut e) = res {
+                e.push_str(format!(
+                    "Note: Variable {} does not match its declaration in the interface here:",
+                    strings.resolve(name)
+                ));
+                e.push_span(*name_span);
+                errors.push(e);
+            }
+        }
+        self.core.revert();
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Returns the names of the top level variables defined by the script, in order of definition.
+    /// On failure, returns every error found in the script, in the order they were found.
+    pub fn check_script(
+        &mut self,
+        strings: &mut lasso::Rodeo,
+        parsed: &[ast::Statement],
+    ) -> std::result::Result<Vec<StringId>, Vec<SyntaxError>> {
         // Tell type checker to start keeping track of changes to the type state so we can roll
         // back all the changes if the script contains an error.
         self.core.save();
+        let graph_start = self.core.num_type_nodes();
+        self.graph_start = graph_start;
         let mark = self.bindings.unwind_point();
+        self.expr_types.clear();
+        self.errors.clear();
+        self.matches.clear();
+        self.prints.clear();
+        self.last_holes.clear();
+        self.generalize_warnings.clear();
+        self.last_graph = None;
 
-        let len = parsed.len();
-        for (i, item) in parsed.iter().enumerate() {
-            let is_last = i == len - 1;
-            if let Err(e) = self.check_statement(strings, item, is_last) {
-                // println!("num type nodes {}", self.core.num_type_nodes());
+        for item in parsed.iter() {
+            let stmt_mark = self.bindings.unwind_point();
+            let num_errors = self.errors.len();
+            self.core.save();
+            // Errors are recorded in self.errors rather than returned
+            let _ = self.check_statement(strings, item);
 
-                // Roll back changes to the type state and bindings
+            if self.errors.len() > num_errors {
+                // Errors in nested statements or undefined variables don't stop the statement itself,
+                // but it still shouldn't affect the rest of the script.
+                self.record_error_graph(strings);
                 self.core.revert();
-                self.bindings.unwind(mark);
-                return Err(e);
+                self.bindings.unwind(stmt_mark);
+                self.bind_never(item);
+            } else {
+                self.core.make_permanent();
             }
         }
 
+        if self.record_graph && self.last_graph.is_none() {
+            self.last_graph = Some(self.core.constraint_graph(strings, graph_start));
+        }
+
+        if !self.errors.is_empty() {
+            // println!("num type nodes {}", self.core.num_type_nodes());
+
+            // Roll back changes to the type state and bindings
+            self.core.revert();
+            self.bindings.unwind(mark);
+            self.expr_types.clear();
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        // If a variable is defined multiple times, only report the last definition
+        let mut defined: Vec<StringId> = Vec::new();
+        for &name in self.bindings.vars.keys_since(&mark.0) {
+            defined.retain(|&n| n != name);
+            defined.push(name);
+        }
+
+        // The same expression may be visited more than once, e.g. when a checked expression has to be
+        // inferred, so only keep the first inferred and the first expected type for each expression.
+        let mut seen = std::collections::HashSet::new();
+        self.expr_types
+            .retain(|&(span, ty)| seen.insert((span, matches!(ty, ExprType::Value(..)))));
+
+        // Holes are only reported for successful scripts since their types are
+        // incomplete when checking stops partway through an expression.
+        if self.record_holes {
+            self.last_holes = self.core.explicit_holes(graph_start);
+            // Annotations may be checked more than once, e.g. for each instantiation of a
+            // polymorphic type, so only keep the first variable for each hole.
+            let mut seen = std::collections::HashSet::new();
+            self.last_holes.retain(|&(span, _)| seen.insert(span));
+        }
+
         // Now that script type-checked successfully, make the global definitions permanent
         // by removing them from the changes rollback list
         self.core.make_permanent();
         self.bindings.make_permanent(mark);
         // println!("num type nodes {}", self.core.num_type_nodes());
         // println!("{} vars {} flows", self.core.varcount, self.core.flowcount);
-        Ok(())
+        Ok(defined)
+    }
+
+    /// Print the inferred type of a top level variable, if it exists.
+    pub fn binding_type(&self, strings: &lasso::Rodeo, name: StringId) -> Option<String> {
+        let val = *self.bindings.vars.get(&name)?;
+        Some(reconstruct::print_value_type(&self.core, strings, val))
+    }
+
+    /// Like binding_type, but always gives a type that the variable can be checked against.
+    pub fn interface_type(&self, strings: &lasso::Rodeo, name: StringId) -> Option<String> {
+        let val = *self.bindings.vars.get(&name)?;
+        Some(reconstruct::print_interface_type(&self.core, strings, val))
+    }
+
+    /// Spans of the print arguments in the last checked script whose values are all i32 or u8.
+    /// These are represented as JS Numbers, so they need to be converted to print as integers.
+    pub fn fixed_width_prints(&self) -> HashSet<Span> {
+        let is_fixed_width =
+            |head: &VTypeHead| matches!(head, &VTypeHead::VAbstract { ty } if ty == self.TY_I32 || ty == self.TY_U8);
+        self.prints
+            .iter()
+            .filter(|&&(_, val)| {
+                let heads: Vec<_> = match self.core.r.get(val.0) {
+                    Some(TypeNode::Value((head, ..))) => vec![head],
+                    Some(TypeNode::Var(_)) => (self.core.r.flows_from(val.0))
+                        .filter_map(|i| match self.core.r.get(i) {
+                            Some(TypeNode::Value((head, ..))) => Some(head),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                !heads.is_empty() && heads.into_iter().all(is_fixed_width)
+            })
+            .map(|&(span, _)| span)
+            .collect()
+    }
+
+    /// Warnings for match arms in the last checked script that can never be taken, based on
+    /// the variants that actually flow into the match. Matches that could receive values from
+    /// code that hasn't been checked yet (e.g. in the body of a top level function) are skipped.
+    pub fn match_warnings(&self, strings: &lasso::Rodeo) -> Vec<Warning> {
+        let mut out = Vec::new();
+        if self.matches.is_empty() {
+            return out;
+        }
+
+        let open = self.core.open_nodes(self.bindings.vars.m.values().copied());
+        for m in self.matches.iter() {
+            if open.contains(&m.bound.0) {
+                continue;
+            }
+            // If no variants flow into the match at all, it is dead code rather than having unreachable arms
+            let Some(tags) = self.core.case_tags_flowing_into(m.bound) else {
+                continue;
+            };
+
+            for &(tag, span) in m.cases.iter() {
+                if !tags.contains(&tag) {
+                    let msg = SyntaxError::new2(
+                        format!(
+                            "Warning [unreachable-match-arm]: Variant `{} can never occur here",
+                            strings.resolve(&tag)
+                        ),
+                        span,
+                        "Note: No value with that tag flows into this match:",
+                        m.arg_span,
+                    );
+                    out.push(Warning::new(WarningKind::UnreachableMatchArm, span, msg));
+                }
+            }
+
+            if let Some(span) = m.wildcard
+                && tags.iter().all(|tag| m.cases.iter().any(|&(t, _)| t == *tag))
+            {
+                let msg = SyntaxError::new1(
+                    "Warning [unreachable-wildcard]: Wildcard pattern can never match, since every variant that can occur here is already handled",
+                    span,
+                );
+                out.push(Warning::new(WarningKind::UnreachableWildcard, span, msg));
+            }
+        }
+        out
+    }
+
+    /// Warnings for the function definitions in the last checked script which could not be generalized
+    pub fn generalize_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.generalize_warnings)
+    }
+
+    pub fn num_type_nodes(&self) -> usize {
+        self.core.num_type_nodes()
+    }
+
+    /// Remove type nodes that are no longer reachable from the global bindings. Expression
+    /// types whose nodes are removed are forgotten. Returns the number of nodes removed.
+    pub fn compact(&mut self) -> usize {
+        let before = self.core.num_type_nodes();
+        let mapping = self.core.compact(self.bindings.vars.m.values().copied());
+        let f = |i: TypeNodeInd| if i == NONE { Some(i) } else { mapping[i.0] };
+
+        for v in self.bindings.vars.m.values_mut() {
+            v.0 = f(v.0).unwrap();
+        }
+        self.expr_types.retain_mut(|(_, ty)| {
+            let new = match *ty {
+                ExprType::Value(v) => f(v.0).map(|i| ExprType::Value(Value(i))),
+                ExprType::Use(u) => f(u.0).map(|i| ExprType::Use(Use(i))),
+            };
+            match new {
+                Some(new) => {
+                    *ty = new;
+                    true
+                }
+                None => false,
+            }
+        });
+        self.matches.clear();
+        self.last_holes.retain_mut(|(_, i)| match f(*i) {
+            Some(new) => {
+                *i = new;
+                true
+            }
+            None => false,
+        });
+
+        before - self.core.num_type_nodes()
+    }
+
+    /// The explicit _ annotations in the last successfully checked script, along with the
+    /// type of values that flowed into each hole and the type each hole was used as.
+    pub fn typed_holes(&self, strings: &lasso::Rodeo) -> Vec<(Span, String, String)> {
+        self.last_holes
+            .iter()
+            .map(|&(span, i)| {
+                let val = reconstruct::print_value_type(&self.core, strings, Value(i));
+                let u = reconstruct::print_use_type(&self.core, strings, Use(i));
+                (span, val, u)
+            })
+            .collect()
+    }
+
+    pub fn expr_types(&self) -> &[(Span, ExprType)] {
+        &self.expr_types
+    }
+
+    /// Print the type of an expression. For checked expressions, this is the type that was expected.
+    pub fn print_expr_type(&self, strings: &lasso::Rodeo, ty: ExprType) -> String {
+        match ty {
+            ExprType::Value(v) => reconstruct::print_value_type(&self.core, strings, v),
+            ExprType::Use(u) => reconstruct::print_use_type(&self.core, strings, u),
+        }
     }
 }
@@ -47,6 +47,11 @@ impl<K: Eq + Hash + Clone, V> UnwindMap<K, V> {
         }
     }
 
+    /// Keys inserted since the given unwind point, in insertion order
+    pub fn keys_since(&self, n: &UnwindPoint) -> impl Iterator<Item = &K> {
+        self.changes[n.0..].iter().map(|(k, _)| k)
+    }
+
     pub fn make_permanent(&mut self, n: UnwindPoint) {
         assert!(n.0 == 0);
         self.changes.clear();
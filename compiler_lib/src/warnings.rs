@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use crate::ast;
+use crate::ast::StringId;
+use crate::spans::Span;
+use crate::spans::SpanManager;
+use crate::spans::SpannedError;
+use crate::unwindmap::UnwindMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    UnusedVariable,
+    UnusedParameter,
+    UnusedPatternVariable,
+    Shadowing,
+    UnreachableMatchArm,
+    UnreachableWildcard,
+    NotGeneralized,
+    IgnoredResult,
+}
+impl WarningKind {
+    pub const ALL: [WarningKind; 8] = [
+        WarningKind::UnusedVariable,
+        WarningKind::UnusedParameter,
+        WarningKind::UnusedPatternVariable,
+        WarningKind::Shadowing,
+        WarningKind::UnreachableMatchArm,
+        WarningKind::UnreachableWildcard,
+        WarningKind::NotGeneralized,
+        WarningKind::IgnoredResult,
+    ];
+
+    pub fn name(self) -> &'static str {
+        use WarningKind::*;
+        match self {
+            UnusedVariable => "unused-variable",
+            UnusedParameter => "unused-parameter",
+            UnusedPatternVariable => "unused-pattern-variable",
+            Shadowing => "shadowing",
+            UnreachableMatchArm => "unreachable-match-arm",
+            UnreachableWildcard => "unreachable-wildcard",
+            NotGeneralized => "not-generalized",
+            IgnoredResult => "ignored-result",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// A diagnostic that does not prevent compilation. Unlike errors, these are
+/// reported alongside successfully compiled code.
+#[derive(Debug)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub span: Span,
+    msg: SpannedError,
+}
+impl Warning {
+    pub fn new(kind: WarningKind, span: Span, msg: SpannedError) -> Self {
+        Self { kind, span, msg }
+    }
+
+    pub fn print(&self, sm: &SpanManager) -> String {
+        self.msg.print(sm)
+    }
+}
+
+struct VarDef {
+    name: StringId,
+    span: Span,
+    // Which warning to give if the variable is never used, if any
+    unused_kind: Option<WarningKind>,
+    used: bool,
+}
+
+/// Checks a script for likely mistakes like unused variables. This is a purely syntactic pass
+/// over the AST, and is only run on scripts that type checked successfully.
+pub struct WarningChecker<'a> {
+    strings: &'a lasso::Rodeo,
+    enabled: &'a HashSet<WarningKind>,
+
+    // Variables in scope, as indices into defs
+    vars: UnwindMap<StringId, usize>,
+    defs: Vec<VarDef>,
+    out: Vec<Warning>,
+}
+impl<'a> WarningChecker<'a> {
+    pub fn new(strings: &'a lasso::Rodeo, enabled: &'a HashSet<WarningKind>) -> Self {
+        Self {
+            strings,
+            enabled,
+            vars: UnwindMap::new(),
+            defs: Vec::new(),
+            out: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, kind: WarningKind, span: Span, msg: SpannedError) {
+        if self.enabled.contains(&kind) {
+            self.out.push(Warning { kind, span, msg });
+        }
+    }
+
+    fn define(&mut self, name: StringId, span: Span, unused_kind: Option<WarningKind>) {
+        // By convention, names starting with an underscore are intentionally unused
+        let name_str = self.strings.resolve(&name);
+        let ignored = name_str.starts_with('_');
+
+        // Top level definitions (those with no unused warning) may be redefined freely
+        if let Some(&old) = self.vars.get(&name)
+            && unused_kind.is_some()
+            && !ignored
+        {
+            let msg = SpannedError::new2(
+                format!("Warning [shadowing]: Variable {} shadows an earlier definition", name_str),
+                span,
+                "Note: The earlier definition is here:",
+                self.defs[old].span,
+            );
+            self.warn(WarningKind::Shadowing, span, msg);
+        }
+
+        let unused_kind = if ignored { None } else { unused_kind };
+        self.vars.insert(name, self.defs.len());
+        self.defs.push(VarDef {
+            name,
+            span,
+            unused_kind,
+            used: false,
+        });
+    }
+
+    fn define_pattern(&mut self, pat: &ast::LetPattern, simple_kind: Option<WarningKind>, top_level: bool) {
+        // Simple variable patterns use the given warning kind, while variables nested in
+        // a destructuring pattern are reported as unused pattern variables.
+        use ast::LetPattern::*;
+        let nested_kind = if top_level {
+            None
+        } else {
+            Some(WarningKind::UnusedPatternVariable)
+        };
+        match pat {
+            Var((Some(name), span), _) => self.define(*name, *span, simple_kind),
+            Var((None, _), _) => {}
+            _ => self.define_pattern_sub(pat, nested_kind),
+        }
+    }
+
+    fn define_pattern_sub(&mut self, pat: &ast::LetPattern, kind: Option<WarningKind>) {
+        use ast::LetPattern::*;
+        match pat {
+            Alias(sub_pat, (name, span), _) => {
+                self.define_pattern_sub(sub_pat, kind);
+                self.define(*name, *span, kind);
+            }
+            Case(_, val_pat) => self.define_pattern_sub(val_pat, kind),
+            Record(((_, pairs), _)) => {
+                for (_, pat) in pairs {
+                    self.define_pattern_sub(pat, kind);
+                }
+            }
+            Var((Some(name), span), _) => self.define(*name, *span, kind),
+            Var((None, _), _) => {}
+        }
+    }
+
+    fn ignored_result(&mut self, expr: &ast::SExpr) {
+        // Only warn for expressions whose sole purpose is to compute a value
+        use ast::Expr::*;
+        match &expr.0 {
+            BinOp(_) | Case(_) | FieldAccess(_) | FuncDef(_) | InstantiateExist(_) | InstantiateUni(_) | Literal(_)
+            | Record(_) | Variable(_) => {
+                let msg = SpannedError::new1(
+                    "Warning [ignored-result]: The value of this expression is ignored, which is likely unintentional. If you did intend to ignore it, do so explicitly via let _ = ...",
+                    expr.1,
+                );
+                self.warn(WarningKind::IgnoredResult, expr.1, msg);
+            }
+            _ => {}
+        }
+    }
+
+    /// `value_used` is true for the last statement of a script, whose value is printed by the REPL.
+    fn statement(&mut self, stmt: &ast::Statement, top_level: bool, value_used: bool) {
+        use ast::Statement::*;
+        let let_kind = if top_level { None } else { Some(WarningKind::UnusedVariable) };
+        match stmt {
+            Empty => {}
+            Expr(expr) => {
+                if !value_used {
+                    self.ignored_result(expr);
+                }
+                self.expr(expr);
+            }
+            LetDef((pat, var_expr)) => {
+                self.expr(var_expr);
+                self.define_pattern(pat, let_kind, top_level);
+            }
+            LetRecDef(defs) => {
+                for (name, (_, span)) in defs {
+                    self.define(*name, *span, let_kind);
+                }
+                for (_, expr) in defs {
+                    self.expr(expr);
+                }
+            }
+            Println(exprs) => {
+                for expr in exprs {
+                    self.expr(expr);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::SExpr) {
+        use ast::Expr::*;
+        match &expr.0 {
+            BinOp(e) => {
+                self.expr(&e.lhs);
+                self.expr(&e.rhs);
+            }
+            Block(e) => {
+                let mark = self.vars.unwind_point();
+                for stmt in e.statements.iter() {
+                    self.statement(stmt, false, false);
+                }
+                self.expr(&e.expr);
+                self.vars.unwind(mark);
+            }
+            Call(e) => {
+                self.expr(&e.func);
+                self.expr(&e.arg);
+            }
+            Case(e) => self.expr(&e.expr),
+            FieldAccess(e) => self.expr(&e.expr),
+            FieldSet(e) => {
+                self.expr(&e.expr);
+                self.expr(&e.value);
+            }
+            FuncDef(e) => {
+                let mark = self.vars.unwind_point();
+                self.define_pattern(&e.param.0, Some(WarningKind::UnusedParameter), false);
+                self.expr(&e.body);
+                self.vars.unwind(mark);
+            }
+            If(e) => {
+                self.expr(&e.cond.0);
+                self.expr(&e.then_expr);
+                self.expr(&e.else_expr);
+            }
+            InstantiateExist(e) => self.expr(&e.expr),
+            InstantiateUni(e) => self.expr(&e.expr),
+            Literal(_) => {}
+            Loop(e) => self.expr(&e.body),
+            Match(e) => {
+                self.expr(&e.expr.0);
+                for ((pat, _), rhs_expr) in e.cases.iter() {
+                    let mark = self.vars.unwind_point();
+                    self.define_pattern(pat, Some(WarningKind::UnusedPatternVariable), false);
+                    self.expr(rhs_expr);
+                    self.vars.unwind(mark);
+                }
+            }
+            Record(e) => {
+                for (_, expr, _, _) in e.fields.iter() {
+                    self.expr(expr);
+                }
+            }
+            Typed(e) => self.expr(&e.expr),
+            Variable(e) => {
+                if let Some(&i) = self.vars.get(&e.name) {
+                    self.defs[i].used = true;
+                }
+            }
+        }
+    }
+
+    pub fn check_script(mut self, parsed: &[ast::Statement]) -> Vec<Warning> {
+        for (i, stmt) in parsed.iter().enumerate() {
+            self.statement(stmt, true, i + 1 == parsed.len());
+        }
+
+        let mut unused = Vec::new();
+        for def in self.defs.iter() {
+            if let (Some(kind), false) = (def.unused_kind, def.used) {
+                unused.push((kind, def.name, def.span));
+            }
+        }
+        for (kind, name, span) in unused {
+            let what = match kind {
+                WarningKind::UnusedParameter => "Function parameter",
+                _ => "Variable",
+            };
+            let msg = SpannedError::new1(
+                format!(
+                    "Warning [{}]: {} {} is never used",
+                    kind.name(),
+                    what,
+                    self.strings.resolve(&name)
+                ),
+                span,
+            );
+            self.warn(kind, span, msg);
+        }
+
+        self.out
+    }
+}
@@ -47,9 +47,11 @@ function initializeRepl(root, compiler, Printer) {
 
     function execCode(script) {
         let compiled;
+        let types;
         try {
             if (!compiler.process(script)) {return [false, compiler.get_err()];}
             compiled = '(' + compiler.get_output() + ')';
+            types = compiler.get_warnings() + compiler.get_binding_types();
         } catch (e) {
             return [false, 'Internal compiler error: ' + e.toString() +
                 '\nIf you see this message, please file an issue on Github with the code required to trigger this error.'];
@@ -62,7 +64,7 @@ function initializeRepl(root, compiler, Printer) {
             if (val !== undefined) {
                 p.visitRoot(val);
             }
-            return [true, p.parts.join('')];
+            return [true, types + p.parts.join('')];
         } catch (e) {
             return [false, 'An error occurred during evaluation in the repl: ' + e.toString()];
         }
@@ -154,6 +156,18 @@ class Printer {
         this.visit(e);
     }
 
+    // Check whether e is a chain of `Cons (head, tail) ending in `Nil
+    isList(e) {
+        const seen = new Set;
+        while (e && e.$tag === 'Cons' && !seen.has(e)) {
+            seen.add(e);
+            const v = e.$val;
+            if (!v || typeof v !== 'object' || Object.keys(v).length !== 2 || !Object.hasOwn(v, '_0') || !Object.hasOwn(v, '_1')) {return false;}
+            e = v._1;
+        }
+        return !!e && e.$tag === 'Nil';
+    }
+
     visit(e) {
         const type = typeof e;
         if (type === 'boolean' || type === 'bigint') {this.push(e.toString()); return;}
@@ -175,7 +189,17 @@ class Printer {
         const LIMIT = 80;
         if (this.current_size > LIMIT) {this.push('...'); return;}
 
-        if (e.$tag) {
+        if (this.isList(e)) {
+            this.push('[');
+            for (let first = true; e.$tag === 'Cons'; e = e.$val._1) {
+                if (!first) {this.push('; ')}
+                first = false;
+                if (this.current_size > LIMIT) {this.push('...'); break;}
+
+                this.visit(e.$val._0);
+            }
+            this.push(']');
+        } else if (e.$tag) {
             this.push(e.$tag);
             if (!e.$val || typeof e.$val !== 'object') {
                 this.push(' ');
@@ -234,4 +258,38 @@ function loop(expr) {
         v = expr();
     }
     return v.$val;
-}
\ No newline at end of file
+}
+
+// Structural equality used by the == and != operators
+function eq(a, b) {
+    if (typeof a === 'function' || typeof b === 'function') {
+        throw new Error('Functions cannot be compared for equality');
+    }
+    if (a === b) {return true;}
+    if (typeof a !== 'object' || typeof b !== 'object') {return false;}
+
+    const keys = Object.keys(a);
+    if (keys.length !== Object.keys(b).length) {return false;}
+    return keys.every(k => Object.hasOwn(b, k) && eq(a[k], b[k]));
+}
+function neq(a, b) {
+    return !eq(a, b);
+}
+
+// Ordering used by the compare builtin, whose arguments are always two primitives of the same type
+function cmp(a, b) {
+    // NaN is ordered before all other floats
+    if (a !== a || b !== b) {return (b !== b) - (a !== a);}
+    return a < b ? -1 : (a > b ? 1 : 0);
+}
+function compare({_0: a, _1: b}) {
+    return BigInt(cmp(a, b));
+}
+
+// Conversions between int and the fixed width integer types
+function i32_of_int(x) {return Number(BigInt.asIntN(32, x));}
+function int_of_i32(x) {return BigInt(x);}
+function i64_of_int(x) {return BigInt.asIntN(64, x);}
+function int_of_i64(x) {return x;}
+function u8_of_int(x) {return Number(BigInt.asUintN(8, x));}
+function int_of_u8(x) {return BigInt(x);}
\ No newline at end of file
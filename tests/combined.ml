@@ -180,8 +180,8 @@ let a = 3;
 let _ = {a: any}.a + 1;
 
 
-### Bad
-// Error on unused <= expressions:
+### Good
+// Unused <= expressions are only an ignored-result warning:
 let x = {a=4; mut b=6; c=9};
 print x; // {a=4; b=6; c=9}
 x.b <= x.b + 11;
@@ -217,3 +217,107 @@ match ref.v with
 | `Some (a: t) -> b a
 | `None _ -> 0
 ;
+
+### Good
+// Alias patterns
+let area = fun shape -> match shape with
+  | `Rect ({w; h} as r) -> w * h + r.depth
+  | `Square (s as side: int) -> s * side;
+let _ = area `Rect {w=2; h=3; depth=1};
+let f = fun ({a} as r) -> a + r.b;
+let ((x, y) as p) = 1, 2;
+let _ = f {a=x; b=p._1};
+// Aliases of variant patterns bind the whole variant
+let unwrap = fun v -> match v with
+  | (`A x as whole) -> (match whole with | `A y -> x + y)
+  | (`B y as other) -> y;
+let _: int = unwrap `A 1 + unwrap `B 2;
+
+### Bad
+// Aliased variant patterns still only match their own tag
+let unwrap = fun v -> match v with
+  | (`A x as whole) -> x;
+let _ = unwrap `B 2;
+
+### Bad
+let ({a} as r) = {a=1};
+let _ = r.b;
+
+### Bad
+let (x as x) = 1;
+
+### Good
+// Structural and physical equality
+let a = {x=1};
+let _: bool * bool * bool = a == {x=1}, a === a, `Some 1 !== `Some 1;
+
+### Good
+// String ordering and the compare builtin
+let _: bool = "abc" <^ "abd";
+let _: int = compare (1, 2) + compare ("b", "a") + compare (2.5, 1.0);
+let cmp = fun (x, y) -> compare (x, y);
+let _: int = cmp (true, false);
+
+### Bad
+let _ = compare ("a", 1);
+
+### Bad
+let _ = compare ({a=1}, {a=2});
+
+### Bad
+let _ = compare ((fun x -> x), (fun x -> x));
+
+### Bad
+let _ = "abc" < "abd";
+
+### Bad
+let _ = 1 <^ 2;
+
+### Good
+// List literals and cons
+let rec sum = fun l -> match l with
+  | [] -> 0
+  | h :: t -> h + sum t;
+let xs = [1; 2; 3];
+let _: int = sum (0 :: xs) + sum ([]);
+let (a :: [b; c]) = xs;
+let _: int = a + b + c;
+
+### Bad
+let rec sum = fun l -> match l with
+  | [] -> 0
+  | h :: t -> h + sum t;
+let _ = sum ["a"];
+
+### Bad
+let [a; b] = [1; 2; 3];
+
+### Good
+// Binding operators
+let ( let* ) = fun (type a r) (x: [`Some a | `None any], f: a -> [`Some r | `None any]): [`Some r | `None any] ->
+  match x with
+  | `None _ -> `None {}
+  | `Some v -> f v;
+let ( and* ) = fun (type a b) (x: [`Some a | `None any], y: [`Some b | `None any]): [`Some (a * b) | `None any] ->
+  match x with
+  | `None _ -> `None {}
+  | `Some a -> (match y with | `None _ -> `None {} | `Some b -> `Some (a, b));
+let _ = (
+  let* x = `Some 1 and* y = `Some 2;
+  let* z = `Some (x + y) in
+  `Some (z * 2)
+);
+
+### Bad
+let ( let* ) = fun (x, f) -> f x;
+let _ = (let* x = 1 in x ^ "a");
+
+### Bad
+let _ = (let* x = `Some 1 in x);
+
+### Good
+// Fixed width operators are not confused with an operator followed by a variable
+let u8x = 3;
+let a = 5;
+let i32_b = 4;
+print 1+u8x, a-i32_b, 10i32-%i32 3i32, -0u8;
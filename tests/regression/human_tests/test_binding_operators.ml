@@ -0,0 +1,23 @@
+let ( let* ) = fun (type a r) (x: [`Some a | `None any], f: a -> [`Some r | `None any]): [`Some r | `None any] ->
+  match x with
+  | `None _ -> `None {}
+  | `Some v -> f v;
+let ( and* ) = fun (type a b) (x: [`Some a | `None any], y: [`Some b | `None any]): [`Some (a * b) | `None any] ->
+  match x with
+  | `None _ -> `None {}
+  | `Some a -> (match y with | `None _ -> `None {} | `Some b -> `Some (a, b));
+let div = fun (a, b) -> if b == 0 then `None {} else `Some (a / b);
+let r = (
+  let* x = div (10, 2);
+  print "x", x;
+  let* y = div (x, 0) in
+  `Some (x + y)
+);
+print r;
+let r2 = (
+  let* x = div (10, 2) and* y = div (9, 3);
+  let* (z: int) = div (x, y);
+  `Some (x + y + z)
+);
+print r2;
+print (let* _ = `Some 1 in `Some "str");
@@ -0,0 +1,8 @@
+print "abc" <^ "abd", "b" >=^ "a", "a" >^ "a", "a" <=^ "a";
+print compare (1, 2), compare ("b", "a"), compare (1.5, 1.5), compare (255u8, 0u8);
+print compare (true, false), compare (3i32, 4i32), compare (2.0, 0.0 /. 0.0);
+
+let rec insert = fun (x, l) -> match l with
+  | `Nil _ -> `Cons {h=x; t=`Nil {}}
+  | `Cons {h; t} -> (if compare (x, h) <= 0 then `Cons {h=x; t=l} else `Cons {h; t=insert (x, t)});
+print insert ("b", insert ("c", insert ("a", `Nil {})));
@@ -0,0 +1,15 @@
+let xs = [1; 2; 3];
+print xs, [], 0 :: xs, [[1]; []];
+
+let rec map = fun (f, l) -> match l with
+  | [] -> []
+  | h :: t -> f h :: map (f, t);
+print map ((fun x -> x * 2), xs);
+
+let rec sum = fun l -> match l with
+  | `Nil _ -> 0
+  | `Cons (h, t) -> h + sum t;
+print sum xs, xs == [1; 2; 3];
+
+let (h :: t) = xs;
+print h, t, `Cons (1, 2);
@@ -0,0 +1,5 @@
+let a = {x=1; y=`Some (2, "three")};
+print a == {y=`Some (2, "three"); x=1}, a != {x=1; y=`None {}};
+print a === a, a === {x=1; y=`Some (2, "three")}, a !== a;
+print `Some 1 == `Some 1, (1, 2.5) == (1, 2.5), 9 == 9.0;
+print {x=1} == {x=1; y=2};
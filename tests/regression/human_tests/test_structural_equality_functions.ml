@@ -0,0 +1,2 @@
+let f = fun x -> x;
+print {f} == {f};
@@ -3,6 +3,7 @@
 #![allow(unused_variables)]
 
 use compiler_lib::CompilationResult;
+use compiler_lib::Limits;
 use compiler_lib::State as CompilerState;
 use wasm_bindgen::prelude::*;
 
@@ -44,6 +45,46 @@ impl State {
         self.err.take()
     }
 
+    /// `val name : type` lines for the variables defined by the last successfully processed script
+    pub fn get_binding_types(&self) -> String {
+        self.s
+            .defined_bindings()
+            .into_iter()
+            .map(|(name, ty)| format!("val {} : {}\n", name, ty))
+            .collect()
+    }
+
+    /// Type of the innermost expression at the given byte offset in the last processed script
+    pub fn type_at(&self, offset: usize) -> Option<String> {
+        self.s.type_at(self.s.last_source_index()?, offset)
+    }
+
+    /// Warnings for the last successfully processed script
+    pub fn get_warnings(&self) -> String {
+        self.s.warnings().into_iter().map(|(_, msg)| msg + "\n").collect()
+    }
+
+    /// Returns false if there is no warning with the given name
+    pub fn set_warning_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.s.set_warning_enabled(name, enabled)
+    }
+
+    pub fn set_limits(&mut self, max_type_nodes: usize, max_flow_work: usize, max_instantiation_depth: usize) {
+        self.s.set_limits(Limits {
+            max_type_nodes,
+            max_flow_work,
+            max_instantiation_depth,
+        });
+    }
+
+    pub fn set_verbose_errors(&mut self, enabled: bool) {
+        self.s.set_verbose_errors(enabled);
+    }
+
+    pub fn set_overloaded_arith(&mut self, enabled: bool) {
+        self.s.set_overloaded_arith(enabled);
+    }
+
     pub fn reset(&mut self) {
         self.s.reset();
     }